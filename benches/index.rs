@@ -0,0 +1,62 @@
+//! Compares `RegionIndex` with a linear scan over 100k intervals.
+//! Run with `cargo bench --bench index`.
+use genomic_range::{RegionIndex, StringRegion};
+use std::time::Instant;
+
+fn main() {
+    let mut seed: u64 = 42;
+    let mut next = move |bound: u64| {
+        seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        (seed >> 33) % bound
+    };
+    let intervals: Vec<StringRegion> = (0..100_000)
+        .map(|_| {
+            let start = next(100_000_000);
+            StringRegion::from_triple(("chr1", start, start + 1 + next(5_000)))
+        })
+        .collect();
+    let queries: Vec<StringRegion> = (0..1_000)
+        .map(|_| {
+            let start = next(100_000_000);
+            StringRegion::from_triple(("chr1", start, start + 1 + next(10_000)))
+        })
+        .collect();
+
+    let now = Instant::now();
+    let index = RegionIndex::from_regions(intervals.clone());
+    println!("{:<22} {:>12?}", "RegionIndex::new", now.elapsed());
+
+    let now = Instant::now();
+    let mut total = 0;
+    for query in &queries {
+        total += intervals
+            .iter()
+            .filter(|region| region.start().max(query.start()) < region.end().min(query.end()))
+            .count();
+    }
+    println!("{:<22} {:>12?} ({})", "linear scan", now.elapsed(), total);
+
+    let now = Instant::now();
+    let mut total = 0;
+    for query in &queries {
+        total += index.query(query).count();
+    }
+    println!(
+        "{:<22} {:>12?} ({})",
+        "RegionIndex::query",
+        now.elapsed(),
+        total
+    );
+
+    let now = Instant::now();
+    let mut total = 0;
+    for query in &queries {
+        total += index.count(query);
+    }
+    println!(
+        "{:<22} {:>12?} ({})",
+        "RegionIndex::count",
+        now.elapsed(),
+        total
+    );
+}
@@ -0,0 +1,79 @@
+//! Compares the allocating and the borrowing region parsers, in time and allocations.
+//! Run with `cargo bench --bench parse`; fails if `BorrowedRegion::parse` allocates.
+use genomic_range::{BorrowedRegion, StringRegion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Prints the timing and returns the number of allocations made since `allocations`.
+fn report(name: &str, now: Instant, allocations: usize, total: u64) -> usize {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<26} {:>12?} {:>10} allocations ({})",
+        name,
+        now.elapsed(),
+        allocations,
+        total
+    );
+    allocations
+}
+
+fn main() {
+    let lines: Vec<String> = (0..20_000)
+        .map(|i| format!("chr{}:{}-{}", i % 22 + 1, i * 10, i * 10 + 500))
+        .collect();
+
+    let (now, allocations) = (Instant::now(), ALLOCATIONS.load(Ordering::Relaxed));
+    let mut total = 0;
+    for line in &lines {
+        total += StringRegion::new(line).unwrap().interval();
+    }
+    report("StringRegion::new", now, allocations, total);
+
+    let (now, allocations) = (Instant::now(), ALLOCATIONS.load(Ordering::Relaxed));
+    let mut total = 0;
+    for line in &lines {
+        let region = BorrowedRegion::parse(line).unwrap();
+        total += region.end - region.start;
+    }
+    let borrowed = report("BorrowedRegion::parse", now, allocations, total);
+    assert_eq!(borrowed, 0, "BorrowedRegion::parse allocated");
+
+    let regions: Vec<StringRegion> = lines
+        .iter()
+        .map(|line| BorrowedRegion::parse(line).unwrap().into_owned())
+        .collect();
+
+    let (now, allocations) = (Instant::now(), ALLOCATIONS.load(Ordering::Relaxed));
+    let mut total = 0;
+    for region in &regions {
+        total += region.clone().interval();
+    }
+    report("StringRegion::clone", now, allocations, total);
+
+    let (now, allocations) = (Instant::now(), ALLOCATIONS.load(Ordering::Relaxed));
+    let mut total = 0;
+    for region in &regions {
+        let view = region.as_borrowed();
+        total += view.end - view.start;
+    }
+    report("StringRegion::as_borrowed", now, allocations, total);
+}
@@ -1,8 +1,182 @@
 use regex::Regex;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
+use std::ops::{Add, Deref, DerefMut, Range, Shl, Shr, Sub};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Biological strand of a feature, independent of the order its coordinates were written in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Strand {
+    Forward,
+    Reverse,
+    Unknown,
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+            Strand::Unknown => write!(f, "."),
+        }
+    }
+}
+
+impl FromStr for Strand {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Strand::from_symbol(s).ok_or_else(|| format!("Invalid strand: {}", s).into())
+    }
+}
+
+impl Strand {
+    fn from_symbol(s: &str) -> Option<Strand> {
+        match s {
+            "+" => Some(Strand::Forward),
+            "-" => Some(Strand::Reverse),
+            "." => Some(Strand::Unknown),
+            _ => None,
+        }
+    }
+
+    /// The strand of the reverse complement; `Unknown` stays `Unknown`.
+    pub fn opposite(self) -> Strand {
+        match self {
+            Strand::Forward => Strand::Reverse,
+            Strand::Reverse => Strand::Forward,
+            Strand::Unknown => Strand::Unknown,
+        }
+    }
+}
+
+/// Error returned by the typed parsing and conversion helpers.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RegionParseError {
+    /// The input does not look like a genomic range at all.
+    InvalidFormat(String),
+    /// A coordinate could not be parsed or is out of range.
+    InvalidPosition(String),
+    /// The contig name is not known to the caller-supplied lookup.
+    UnknownContig(String),
+}
+
+impl fmt::Display for RegionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionParseError::InvalidFormat(s) => write!(f, "Invalid genomic range: {}", s),
+            RegionParseError::InvalidPosition(s) => write!(f, "Invalid position: {}", s),
+            RegionParseError::UnknownContig(s) => write!(f, "Unknown contig: {}", s),
+        }
+    }
+}
+
+impl Error for RegionParseError {}
+
+/// Error returned by set operations on regions, such as `Region::try_union` and
+/// `intersect_sorted`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RegionError {
+    /// The regions lie on different references.
+    DifferentReference,
+    /// The regions are on the same reference but neither overlap nor touch.
+    Disjoint,
+    /// An input required to be sorted is not. `input` tells which one for functions taking
+    /// several (the argument or source position, 0 otherwise) and `index` is the position of
+    /// the first out-of-order element within it.
+    Unsorted { input: usize, index: usize },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::DifferentReference => write!(f, "Regions are on different references"),
+            RegionError::Disjoint => write!(f, "Regions neither overlap nor touch"),
+            RegionError::Unsorted { input, index } => write!(
+                f,
+                "Regions of input {} are not sorted at index {}",
+                input, index
+            ),
+        }
+    }
+}
+
+impl Error for RegionError {}
+
+/// Contig lengths keyed by path, used to clamp regions at the contig end.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ChromSizes {
+    sizes: HashMap<String, u64>,
+}
+
+impl ChromSizes {
+    pub fn new() -> Self {
+        ChromSizes::default()
+    }
+
+    /// Parses a `chrom.sizes` file: one `name<whitespace>length` pair per line. Blank lines
+    /// and lines starting with `#` are skipped. Lengths must be plain integers, see
+    /// `parse_integer_coordinate`.
+    pub fn parse(text: &str) -> Result<Self, RegionParseError> {
+        let mut sizes = ChromSizes::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (path, len) = match (fields.next(), fields.next()) {
+                (Some(path), Some(len)) => (path, len),
+                _ => return Err(RegionParseError::InvalidFormat(line.to_string())),
+            };
+            let len = parse_integer_coordinate(len)?;
+            sizes.insert(path, len);
+        }
+        Ok(sizes)
+    }
+
+    pub fn insert<S: Into<String>>(&mut self, path: S, len: u64) {
+        self.sizes.insert(path.into(), len);
+    }
+
+    pub fn get(&self, path: &str) -> Option<u64> {
+        self.sizes.get(path).copied()
+    }
+
+    /// Like `get`, failing with `UnknownContig` for a missing path.
+    pub fn require(&self, path: &str) -> Result<u64, RegionParseError> {
+        self.get(path)
+            .ok_or_else(|| RegionParseError::UnknownContig(path.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+}
+
+impl<S: Into<String>> std::iter::FromIterator<(S, u64)> for ChromSizes {
+    fn from_iter<I: IntoIterator<Item = (S, u64)>>(iter: I) -> Self {
+        let mut sizes = ChromSizes::new();
+        for (path, len) in iter {
+            sizes.insert(path, len);
+        }
+        sizes
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OptionalRegion {
     pub path: String,
     pub start: Option<u64>,
@@ -23,6 +197,9 @@ impl fmt::Display for OptionalRegion {
 }
 
 impl OptionalRegion {
+    /// Absolute length `|end - start|`, whichever way the bounds were written, or `None` when
+    /// a bound is missing. Unlike `StringRegion`, the bounds are stored as given, so an
+    /// inverted region still reports a positive length here; see `signed_interval`.
     pub fn interval(&self) -> Option<u64> {
         if let Some(start) = self.start {
             if let Some(end) = self.end {
@@ -36,6 +213,22 @@ impl OptionalRegion {
         None
     }
 
+    /// `end - start` as a signed length, negative when inverted. `None` when a bound is
+    /// missing or the length does not fit in an `i64`.
+    pub fn signed_interval(&self) -> Option<i64> {
+        let (start, end) = (self.start?, self.end?);
+        if start <= end {
+            i64::try_from(end - start).ok()
+        } else {
+            i64::try_from(start - end).ok().map(|len| -len)
+        }
+    }
+
+    /// Same as `interval`, named to contrast with `signed_interval`.
+    pub fn abs_interval(&self) -> Option<u64> {
+        self.interval()
+    }
+
     pub fn inverted(&self) -> Option<bool> {
         if let Some(_start) = self.start {
             if let Some(_end) = self.end {
@@ -46,12 +239,13 @@ impl OptionalRegion {
     }
 
     pub fn new_with_prefix(path: String, chr_prefix: &str) -> Result<Self, Box<dyn Error>> {
-        let re = Regex::new(r"^(.+):(\d*)-?(\d*)$").unwrap();
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+):((?:{0})?)-?((?:{0})?)$");
         let caps = re.captures(&path).ok_or("Invalid genomic range")?;
         let mut path_str = caps.get(1).ok_or("Parse Path Error")?.as_str();
 
         let path_string: String;
-        if chr_prefix.len() == 0 {
+        if chr_prefix.is_empty() {
             if path_str.starts_with("chr") {
                 path_str = &path_str[3..];
             }
@@ -66,74 +260,238 @@ impl OptionalRegion {
                 path_string = path_str.to_string()
             }
         }
-        let start = caps.get(2).and_then(|t| t.as_str().parse::<u64>().ok());
-        let end = caps.get(3).and_then(|t| t.as_str().parse::<u64>().ok());
-        return Ok(OptionalRegion {
+        let start = optional_coordinate(caps.get(2))?;
+        let end = optional_coordinate(caps.get(3))?;
+        Ok(OptionalRegion {
             path: path_string,
-            start: start,
-            end: end,
-        });
+            start,
+            end,
+        })
     }
 
     pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
-        let re = Regex::new(r"^(.+):(\d*)-?(\d*)$").unwrap();
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+):((?:{0})?)-?((?:{0})?)$");
         let caps = re.captures(path).ok_or("Invalid genomic range")?;
         let path = caps.get(1).ok_or("Parse Path Error")?;
-        let start = caps.get(2).and_then(|t| t.as_str().parse::<u64>().ok());
-        let end = caps.get(3).and_then(|t| t.as_str().parse::<u64>().ok());
-        return Ok(OptionalRegion {
+        let start = optional_coordinate(caps.get(2))?;
+        let end = optional_coordinate(caps.get(3))?;
+        Ok(OptionalRegion {
             path: path.as_str().to_string(),
-            start: start,
-            end: end,
-        });
+            start,
+            end,
+        })
+    }
+
+    /// Converts into a concrete `StringRegion`, filling a missing start with 0 and a missing
+    /// end with the contig length. `contig_len` is only consulted when the end is missing; a
+    /// start past that length fails with `InvalidPosition` rather than producing an inverted
+    /// region.
+    pub fn resolve<F: Fn(&str) -> Option<u64>>(
+        &self,
+        contig_len: F,
+    ) -> Result<StringRegion, RegionParseError> {
+        let start = self.start.unwrap_or(0);
+        let end = match self.end {
+            Some(end) => end,
+            None => {
+                let len = contig_len(&self.path)
+                    .ok_or_else(|| RegionParseError::UnknownContig(self.path.clone()))?;
+                if len < start {
+                    return Err(RegionParseError::InvalidPosition(format!(
+                        "start {} is past the end of {} ({})",
+                        start, self.path, len
+                    )));
+                }
+                len
+            }
+        };
+        Ok(StringRegion::new_inner(self.path.clone(), start, end))
+    }
+
+    /// Half-open bounds with the open-ended semantics used by the overlap helpers: a missing
+    /// start is 0 and a missing end is unbounded (`None`). Inverted bounds are swapped.
+    fn bounds(&self) -> (u64, Option<u64>) {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => (start.min(end), Some(start.max(end))),
+            (start, end) => (start.unwrap_or(0), end),
+        }
+    }
+
+    fn overlaps_bounds(&self, path: &str, start: u64, end: Option<u64>) -> bool {
+        let (self_start, self_end) = self.bounds();
+        let lo = self_start.max(start);
+        let below_end = |bound: Option<u64>| bound.is_none_or(|b| lo < b);
+        self.path == path && below_end(self_end) && below_end(end)
+    }
+
+    /// Whether the region shares a base with `other`. Unlike the concrete types, a missing
+    /// start means "from position 0" and a missing end means "to the end of the contig", so
+    /// `chr1` overlaps everything on `chr1` and `chr1:100` everything ending after 100.
+    pub fn overlaps(&self, other: &StringRegion) -> bool {
+        self.overlaps_bounds(&other.path, other.start, Some(other.end))
+    }
+
+    /// Same as `overlaps`, with the open-ended semantics applied to both sides.
+    pub fn overlaps_optional(&self, other: &OptionalRegion) -> bool {
+        let (start, end) = other.bounds();
+        self.overlaps_bounds(&other.path, start, end)
+    }
+
+    /// Whether `pos` lies in the region on `path`, with the open-ended semantics of `overlaps`:
+    /// `chr1:100-` contains every `pos >= 100`, `chr1:-200` every `pos < 200`, and a bare
+    /// `chr1:` every position on `chr1`. The path is checked as well, so callers holding only
+    /// a position pass `&region.path`.
+    pub fn contains_pos(&self, path: &str, pos: u64) -> bool {
+        self.overlaps_bounds(path, pos, Some(pos.saturating_add(1)))
     }
 
     pub fn uuid(self: &OptionalRegion) -> String {
-        return format!("{}", self);
+        format!("{}", self)
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// A region on a named contig.
+///
+/// The optional feature `name` (BED column 4) is carried along but ignored by `Display` and by
+/// `PartialEq`, so two records describing the same locus compare equal whatever they are called.
+/// Use `eq_with_name` when the name has to match as well. The BED `score` and the `system`
+/// marker are ignored the same way. Any ordering or hashing of regions follows the same rule and
+/// looks at coordinates only.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StringRegion {
     pub path: String, // Requires no prefix
     pub start: u64,
     pub end: u64,
     inverted: bool,
+    strand: Option<Strand>,
+    name: Option<String>,
+    score: Option<f64>,
+    system: Option<CoordinateSystem>,
+}
+
+impl PartialEq for StringRegion {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.start == other.start
+            && self.end == other.end
+            && self.inverted == other.inverted
+            && self.strand == other.strand
+    }
 }
 
 impl fmt::Display for StringRegion {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Use `self.number` to refer to each positional data point.
         if self.inverted {
-            write!(f, "{}:{}-{}", self.path, self.end, self.start)
+            write!(f, "{}:{}-{}", self.path, self.end, self.start)?;
         } else {
-            write!(f, "{}:{}-{}", self.path, self.start, self.end)
+            write!(f, "{}:{}-{}", self.path, self.start, self.end)?;
+        }
+        if let Some(strand) = self.strand {
+            write!(f, ":{}", strand)?;
         }
+        Ok(())
     }
 }
 
 impl StringRegion {
+    /// Number of bases covered, `end - start`. The bounds are normalized on construction, so
+    /// this is never negative and ignores `inverted`; see `signed_interval`.
     pub fn interval(&self) -> u64 {
-        return self.end - self.start;
+        self.end - self.start
+    }
+    /// `interval()` as a signed length, negative when the region is inverted. `None` when the
+    /// length does not fit in an `i64`, as for `OptionalRegion::signed_interval`.
+    pub fn signed_interval(&self) -> Option<i64> {
+        let len = i64::try_from(self.interval()).ok()?;
+        Some(if self.inverted { -len } else { len })
+    }
+    /// Same as `interval`, named to contrast with `signed_interval`.
+    pub fn abs_interval(&self) -> u64 {
+        self.interval()
+    }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// A copy on `path`, keeping the coordinates, orientation, strand and annotations.
+    pub fn with_path<S: Into<String>>(&self, path: S) -> StringRegion {
+        let mut region = self.clone();
+        region.path = path.into();
+        region
+    }
+    /// A `BorrowedRegion` view sharing this region's path, for code written against borrowed
+    /// regions that should not clone the path.
+    pub fn as_borrowed(&self) -> BorrowedRegion<'_> {
+        BorrowedRegion {
+            path: &self.path,
+            start: self.start,
+            end: self.end,
+            inverted: self.inverted,
+            strand: self.strand,
+        }
     }
     pub fn inverted(&self) -> bool {
         self.inverted
     }
+    /// Toggles the display orientation; the covered bases and the strand are unchanged.
+    pub fn reverse(&mut self) {
+        self.inverted = !self.inverted;
+    }
+    /// A copy with the orientation toggled, as `reverse`.
+    pub fn reversed(&self) -> StringRegion {
+        let mut region = self.clone();
+        region.reverse();
+        region
+    }
+    /// Biological strand, if known. Orthogonal to `inverted`, which only records display order.
+    pub fn strand(&self) -> Option<Strand> {
+        self.strand
+    }
+    pub fn set_strand(&mut self, strand: Option<Strand>) {
+        self.strand = strand;
+    }
+    /// Coordinate system the region was recorded in by its constructor, `None` when unknown.
+    /// `StringRegion::new` stores the numbers as written and leaves it unset; `parse_with`
+    /// records `ParseOptions::system`, the BED and GFF constructors and the conversion methods
+    /// record `ZeroHalfOpen` or `OneClosed`.
+    pub fn system(&self) -> Option<CoordinateSystem> {
+        self.system
+    }
+    /// Feature name, as read from BED column 4.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+    /// Feature score, as read from BED column 5.
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+    pub fn set_score(&mut self, score: Option<f64>) {
+        self.score = score;
+    }
+    /// Like `==`, but the names must match too.
+    pub fn eq_with_name(&self, other: &StringRegion) -> bool {
+        self == other && self.name == other.name
+    }
     pub fn start(&self) -> u64 {
         self.start
     }
     pub fn left(&self) -> u64 {
         if self.inverted {
-            return self.end
+            self.end
         } else {
-            return self.start
+            self.start
         }
     }
     pub fn right(&self) -> u64 {
         if self.inverted {
-            return self.start
+            self.start
         } else {
-            return self.end
+            self.end
         }
     }
 
@@ -142,23 +500,236 @@ impl StringRegion {
     }
 
     pub fn extend(&mut self, len: u64) {
-        self.start -= len;
-        if self.start <= 0 {
-            self.start = 0;
+        *self = self.pad(len, len);
+    }
+
+    /// Same as `extend`, but `end` never passes `contig_len`. A region lying entirely past the
+    /// contig collapses to an empty region at `contig_len`.
+    pub fn extend_capped(&mut self, len: u64, contig_len: u64) {
+        self.extend(len);
+        self.end = self.end.min(contig_len);
+        self.start = self.start.min(self.end);
+    }
+
+    /// A copy grown by `left` bases before `start` (clipped at 0) and `right` bases after
+    /// `end`, like `bedtools slop -l -r`. Orientation is preserved.
+    pub fn pad(&self, left: u64, right: u64) -> StringRegion {
+        let mut region = self.clone();
+        region.start = self.start.saturating_sub(left);
+        region.end = self.end.saturating_add(right);
+        region
+    }
+
+    /// Same as `pad`, with the amounts given in biological orientation like `bedtools slop -s`:
+    /// on the reverse strand `upstream` is added after `end`. The strand is resolved as in
+    /// `upstream`.
+    pub fn pad_stranded(&self, upstream: u64, downstream: u64) -> StringRegion {
+        if self.is_reverse() {
+            self.pad(downstream, upstream)
+        } else {
+            self.pad(upstream, downstream)
+        }
+    }
+
+    /// Same as `pad`, also clipping `end` at the contig length.
+    pub fn pad_within(
+        &self,
+        left: u64,
+        right: u64,
+        sizes: &ChromSizes,
+    ) -> Result<StringRegion, RegionParseError> {
+        let contig_len = sizes.require(&self.path)?;
+        let mut region = self.pad(left, right);
+        region.end = region.end.min(contig_len);
+        region.start = region.start.min(region.end);
+        Ok(region)
+    }
+    /// Center position, `start + interval() / 2`: the middle base for odd lengths and the
+    /// upper of the two central bases for even lengths. Orientation is ignored.
+    pub fn midpoint(&self) -> u64 {
+        self.start + self.interval() / 2
+    }
+
+    /// A copy of `new_len` bases around the same midpoint, clipped at position 0. The
+    /// orientation, strand and annotations are preserved.
+    pub fn resize_centered(&self, new_len: u64) -> StringRegion {
+        let (start, end) = centered_bounds(self.midpoint(), new_len);
+        let mut region = self.clone();
+        region.start = start;
+        region.end = end;
+        region
+    }
+
+    /// Same as `resize_centered`, also clipping the end at the contig length.
+    pub fn resize_centered_within(
+        &self,
+        new_len: u64,
+        sizes: &ChromSizes,
+    ) -> Result<StringRegion, RegionParseError> {
+        let contig_len = sizes.require(&self.path)?;
+        let mut region = self.resize_centered(new_len);
+        region.end = region.end.min(contig_len);
+        region.start = region.start.min(region.end);
+        Ok(region)
+    }
+
+    /// Moves both ends by `delta`, saturating each at 0 and `u64::MAX`: a region pushed past
+    /// the origin shrinks against it instead of wrapping. Orientation is preserved.
+    pub fn shift(&self, delta: i64) -> StringRegion {
+        let mut region = self.clone();
+        region.start = shift_saturating(self.start, delta);
+        region.end = shift_saturating(self.end, delta);
+        region
+    }
+
+    /// Moves both ends by `delta`, failing with `InvalidPosition` instead of saturating.
+    pub fn try_shift(&self, delta: i64) -> Result<StringRegion, RegionParseError> {
+        match (
+            shift_checked(self.start, delta),
+            shift_checked(self.end, delta),
+        ) {
+            (Some(start), Some(end)) => {
+                let mut region = self.clone();
+                region.start = start;
+                region.end = end;
+                Ok(region)
+            }
+            _ => Err(shift_error(self.start, self.end, delta)),
+        }
+    }
+
+    /// Same as `shift`, also saturating both ends at the contig length.
+    pub fn shift_within(
+        &self,
+        delta: i64,
+        sizes: &ChromSizes,
+    ) -> Result<StringRegion, RegionParseError> {
+        let contig_len = sizes.require(&self.path)?;
+        let mut region = self.shift(delta);
+        region.start = region.start.min(contig_len);
+        region.end = region.end.min(contig_len);
+        Ok(region)
+    }
+
+    /// The same bases counted on the reverse complement of a `chrom_len`-bp contig:
+    /// `[chrom_len - end, chrom_len - start)`, with a `+`/`-` strand flipped. Applying it twice
+    /// gives back the original region. Fails with `InvalidPosition` if `end > chrom_len`.
+    pub fn reverse_coordinates(&self, chrom_len: u64) -> Result<StringRegion, RegionParseError> {
+        let (start, end) = mirror_bounds(self.start, self.end, chrom_len)?;
+        let mut region = self.clone();
+        region.start = start;
+        region.end = end;
+        region.strand = self.strand.map(Strand::opposite);
+        Ok(region)
+    }
+
+    /// Same as `reverse_coordinates`, looking up the contig length in `sizes`.
+    pub fn reverse_coordinates_within(
+        &self,
+        sizes: &ChromSizes,
+    ) -> Result<StringRegion, RegionParseError> {
+        self.reverse_coordinates(sizes.require(&self.path)?)
+    }
+
+    /// The `len` bases immediately before `start`, clipped at position 0. The flank keeps the
+    /// orientation, strand and annotations of `self`.
+    pub fn flank_left(&self, len: u64) -> StringRegion {
+        let mut region = self.clone();
+        region.start = self.start.saturating_sub(len);
+        region.end = self.start;
+        region
+    }
+
+    /// The `len` bases immediately after `end`.
+    pub fn flank_right(&self, len: u64) -> StringRegion {
+        let mut region = self.clone();
+        region.start = self.end;
+        region.end = self.end.saturating_add(len);
+        region
+    }
+
+    /// Whether upstream lies at higher coordinates: the strand decides when it is `+` or `-`,
+    /// otherwise (no strand or `.`) an inverted region is treated as reverse.
+    fn is_reverse(&self) -> bool {
+        match self.strand {
+            Some(Strand::Forward) => false,
+            Some(Strand::Reverse) => true,
+            Some(Strand::Unknown) | None => self.inverted,
+        }
+    }
+
+    /// The `len` bases before the biological start: `flank_left` on the forward strand,
+    /// `flank_right` on the reverse strand (see `strand` and `inverted`).
+    pub fn upstream(&self, len: u64) -> StringRegion {
+        if self.is_reverse() {
+            self.flank_right(len)
+        } else {
+            self.flank_left(len)
+        }
+    }
+
+    /// The `len` bases after the biological end; the mirror of `upstream`.
+    pub fn downstream(&self, len: u64) -> StringRegion {
+        if self.is_reverse() {
+            self.flank_left(len)
+        } else {
+            self.flank_right(len)
         }
-        self.end += len;
+    }
+
+    /// Same as `upstream`, also clipping at the contig length. The flag is true when the
+    /// flank is shorter than `len` because it hit either end of the contig.
+    pub fn upstream_within(
+        &self,
+        len: u64,
+        sizes: &ChromSizes,
+    ) -> Result<(StringRegion, bool), RegionParseError> {
+        self.clip_flank(self.upstream(len), len, sizes)
+    }
+
+    /// Same as `downstream`, also clipping at the contig length. The flag is true when the
+    /// flank is shorter than `len`.
+    pub fn downstream_within(
+        &self,
+        len: u64,
+        sizes: &ChromSizes,
+    ) -> Result<(StringRegion, bool), RegionParseError> {
+        self.clip_flank(self.downstream(len), len, sizes)
+    }
+
+    fn clip_flank(
+        &self,
+        mut flank: StringRegion,
+        len: u64,
+        sizes: &ChromSizes,
+    ) -> Result<(StringRegion, bool), RegionParseError> {
+        let contig_len = sizes.require(&self.path)?;
+        flank.end = flank.end.min(contig_len);
+        flank.start = flank.start.min(flank.end);
+        let truncated = flank.interval() < len;
+        Ok((flank, truncated))
+    }
+
+    /// A copy moved into local coordinates: `start` becomes 0 and the length, path and
+    /// orientation are preserved.
+    pub fn shift_to_zero(&self) -> StringRegion {
+        let mut region = self.clone();
+        region.end -= region.start;
+        region.start = 0;
+        region
     }
     // It is used on converting dna-sequence region to bed-style region.
     pub fn start_minus(&mut self) {
-        self.start = self.start - 1;
+        self.start -= 1;
     }
 
     pub fn new_with_prefix(path: String, chr_prefix: &str) -> Result<Self, Box<dyn Error>> {
-        let re = Regex::new(r"^(.+):(\d+)-?(\d*)$").unwrap();
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+):({0})-?((?:{0})?)(?::([+\-.]))?$");
         let caps = re.captures(&path).ok_or("Invalid genomic range")?;
         let mut path_str = caps.get(1).ok_or("Parse Path Error")?.as_str();
         let path_string: String;
-        if chr_prefix.len() == 0 {
+        if chr_prefix.is_empty() {
             if path_str.starts_with("chr") {
                 path_str = &path_str[3..];
             }
@@ -175,217 +746,7335 @@ impl StringRegion {
         }
         let start = caps.get(2).ok_or("Parse Start Position Error")?;
         let end = caps.get(3).ok_or("Parse end Position Error")?;
-        let start_str: &str = start.as_str().as_ref();
-        let end_str: &str = end.as_str().as_ref();
-        let start_u64: u64 = start_str
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        let end_u64: u64 = end_str
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        Ok(StringRegion::new_inner(path.to_string(), start_u64, end_u64))
-    }
-
-    fn new_regexp(path: &str) -> Result<Self, Box<dyn Error>> {
-        let re = Regex::new(r"^(.+):(\d+)-?(\d*)$").unwrap();
+        let start_u64 = parse_coordinate(start.as_str())?;
+        let end_u64 = parse_coordinate(end.as_str())?;
+        let mut region = StringRegion::new_inner(path_string, start_u64, end_u64);
+        if let Some(strand) = caps.get(4) {
+            region.strand = Some(strand.as_str().parse::<Strand>()?);
+        }
+        Ok(region)
+    }
+
+    fn new_regexp(path: &str, unit_exp: i64) -> Result<Self, Box<dyn Error>> {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+):({0})-?((?:{0})?)(?::([+\-.]))?$");
         let caps = re.captures(path).ok_or("Invalid genomic range")?;
         let path = caps.get(1).ok_or("Parse Path Error")?;
         let start = caps.get(2).ok_or("Parse Start Position Error")?;
         let end = caps.get(3).ok_or("Parse end Position Error")?;
-        let start_str: &str = start.as_str().as_ref();
-        let end_str: &str = end.as_str().as_ref();
-        let start_u64: u64 = start_str
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        let end_u64: u64 = end_str
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        Ok(StringRegion::new_inner(path.as_str().to_string(), start_u64, end_u64))
+        let start_u64 = parse_scaled_coordinate(start.as_str(), unit_exp)?;
+        let end_u64 = parse_scaled_coordinate(end.as_str(), unit_exp)?;
+        let mut region = StringRegion::new_inner(path.as_str().to_string(), start_u64, end_u64);
+        if let Some(strand) = caps.get(4) {
+            region.strand = Some(strand.as_str().parse::<Strand>()?);
+        }
+        Ok(region)
     }
 
+    /// Parses `chr:start-end[:strand]` or whitespace separated `chr start end`. Either form may
+    /// be followed by a single unit word: `bp` is ignored, while `kb` and `Mb`
+    /// (case-insensitive) scale both coordinates, so `chr1:1-2 Mb` is `chr1:1000000-2000000`.
+    /// The unit must come directly after `chr:start-end` or after exactly three space separated
+    /// fields; tab separated input is a BED record, whose fourth column is a name even when it
+    /// reads `kb`. Each coordinate is read with `parse_coordinate`, so `chr1:1,000-1.5k` also
+    /// works, and the unit applies before the whole-number check: `chr1:1.5-2 Mb` is valid.
     pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
-        let caps: Vec<&str> = path.split_whitespace().collect();
-        if caps.len() < 3 {
-            return StringRegion::new_regexp(path);
-        }
-        let path = caps[0];
-        let start = caps[1];
-        let end = caps[2];
-        let start_u64: u64 = start
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        let end_u64: u64 = end
-            .parse::<u64>()
-            .map_err(|e| "Parse Int Error, ".to_string() + &e.to_string())?;
-        Ok(StringRegion::new_inner(path.to_string(), start_u64, end_u64))
+        let (body, unit_exp) = split_unit_word(path);
+        let fields: Vec<&str> = body.split_whitespace().collect();
+        if fields.len() < 3 {
+            return StringRegion::new_regexp(body, unit_exp);
+        }
+        Ok(StringRegion::new_inner(
+            fields[0].to_string(),
+            parse_scaled_coordinate(fields[1], unit_exp)?,
+            parse_scaled_coordinate(fields[2], unit_exp)?,
+        ))
+    }
+
+    /// Parses like `new` but strips a leading `chr` from the path, returning whether it was
+    /// present so the caller can re-emit the region in the user's original style.
+    pub fn parse_infer(s: &str) -> Result<(StringRegion, bool), RegionParseError> {
+        let mut region =
+            StringRegion::new(s).map_err(|e| RegionParseError::InvalidFormat(e.to_string()))?;
+        let had_chr_prefix = region.path.starts_with("chr");
+        if had_chr_prefix {
+            region.path = region.path[3..].to_string();
+        }
+        Ok((region, had_chr_prefix))
+    }
+
+    /// Parses like `new`, then replaces the path with the canonical contig name returned by
+    /// `resolve`, e.g. mapping `MT`, `chrM` and `NC_012920.1` to one name. A name `resolve`
+    /// does not know fails with `UnknownContig`.
+    pub fn parse_with_aliases<F>(s: &str, resolve: F) -> Result<StringRegion, RegionParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut region =
+            StringRegion::new(s).map_err(|e| RegionParseError::InvalidFormat(e.to_string()))?;
+        region.path = resolve(&region.path)
+            .ok_or_else(|| RegionParseError::UnknownContig(region.path.clone()))?;
+        Ok(region)
+    }
+
+    /// The first `path:start-end[:strand]` token embedded in `s`, e.g. the locus in a BED name
+    /// such as `gene|chr1:1000-2000`. The path runs back to the nearest whitespace or `|`, `,`,
+    /// `;`, `:`, `=` or bracket, and a comma right after the token is punctuation, not part of
+    /// the end. Tokens that match but fail to parse are skipped; `None` if nothing region-like
+    /// is found.
+    pub fn extract_embedded(s: &str) -> Option<StringRegion> {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        coordinate_regex(&RE, r"[^\s|,;:=()\[\]]+:{0}-{0}(?::[+\-.])?")
+            .find_iter(s)
+            .find_map(|token| StringRegion::new(token.as_str().trim_end_matches(',')).ok())
+    }
+
+    /// Parses `chr:start-end` also written with other separators, such as `chr1:100..200` or
+    /// `chr1_100_200`: the path ends at a `:`, `-` or `_` and the coordinates are separated by
+    /// `-`, `..` or `_`. The coordinates are taken from the end of the input, so contig names
+    /// containing underscores (`chrUn_KI270302v1_100_200`) keep them.
+    pub fn parse_relaxed(s: &str) -> Result<StringRegion, RegionParseError> {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+)[:_\-]({0})(?:-|\.\.|_)({0})$");
+        let caps = re
+            .captures(s.trim())
+            .ok_or_else(|| RegionParseError::InvalidFormat(s.to_string()))?;
+        Ok(StringRegion::new_inner(
+            caps[1].to_string(),
+            parse_coordinate(&caps[2])?,
+            parse_coordinate(&caps[3])?,
+        ))
+    }
+
+    /// Parses like `new`, storing the coordinates as written and recording `opts.system` as
+    /// the region's `system`, so later code can tell which convention it holds.
+    pub fn parse_with(s: &str, opts: &ParseOptions) -> Result<StringRegion, RegionParseError> {
+        let mut region =
+            StringRegion::new(s).map_err(|e| RegionParseError::InvalidFormat(e.to_string()))?;
+        region.system = opts.system;
+        Ok(region)
+    }
+
+    /// Parses like `new`, reading the coordinates in `system` and converting them to this
+    /// crate's 0-based half-open convention.
+    pub fn parse_as(s: &str, system: CoordinateSystem) -> Result<StringRegion, RegionParseError> {
+        StringRegion::new(s)
+            .map_err(|e| RegionParseError::InvalidFormat(e.to_string()))?
+            .to_zero_half_open(system)
+    }
+
+    /// Reinterprets coordinates written in `from` as 0-based half-open. Converting from
+    /// `OneClosed` moves `start` down by one and fails with `InvalidPosition` for a start of
+    /// 0, which does not exist in that system. The result is marked `ZeroHalfOpen`. The
+    /// inverse of `to_one_closed`.
+    pub fn to_zero_half_open(
+        &self,
+        from: CoordinateSystem,
+    ) -> Result<StringRegion, RegionParseError> {
+        let mut region = self.clone();
+        region.system = Some(CoordinateSystem::ZeroHalfOpen);
+        if from == CoordinateSystem::OneClosed {
+            region.start = self.start.checked_sub(1).ok_or_else(|| {
+                RegionParseError::InvalidPosition(
+                    "position 0 does not exist in 1-based coordinates".to_string(),
+                )
+            })?;
+        }
+        Ok(region)
+    }
+
+    /// Writes 0-based half-open coordinates (or coordinates already in `from`) as 1-based
+    /// closed: `start` moves up by one. An empty region has no closed form and fails with
+    /// `InvalidPosition`. The result is marked `OneClosed`. The inverse of `to_zero_half_open`.
+    pub fn to_one_closed(&self, from: CoordinateSystem) -> Result<StringRegion, RegionParseError> {
+        let mut region = self.clone();
+        region.system = Some(CoordinateSystem::OneClosed);
+        if from == CoordinateSystem::ZeroHalfOpen {
+            if self.start == self.end {
+                return Err(RegionParseError::InvalidPosition(
+                    "an empty region has no 1-based closed form".to_string(),
+                ));
+            }
+            region.start += 1;
+        }
+        Ok(region)
+    }
+
+    /// Parses every line with `new`, collecting the regions that parse and the index and error
+    /// of those that do not, so all bad lines can be reported at once.
+    pub fn parse_all_lenient(
+        lines: &[&str],
+    ) -> (Vec<StringRegion>, Vec<(usize, RegionParseError)>) {
+        let mut regions = vec![];
+        let mut errors = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            match StringRegion::new(line) {
+                Ok(region) => regions.push(region),
+                Err(e) => errors.push((i, RegionParseError::InvalidFormat(e.to_string()))),
+            }
+        }
+        (regions, errors)
+    }
+
+    /// Builds a region from already split BED columns (`chrom start end [name score strand]`).
+    /// The name, score and strand are taken from columns 4, 5 and 6 when present; a `.` name or
+    /// score is treated as missing. Scores may be integers or decimals, while positions must
+    /// be plain integers (see `parse_integer_coordinate`).
+    pub fn from_bed_fields(fields: &[&str]) -> Result<Self, Box<dyn Error>> {
+        if fields.len() < 3 {
+            return Err("BED record requires at least 3 fields".into());
+        }
+        let start_u64 = parse_integer_coordinate(fields[1])?;
+        let end_u64 = parse_integer_coordinate(fields[2])?;
+        let mut region = StringRegion::new_inner(fields[0].to_string(), start_u64, end_u64);
+        region.name = fields
+            .get(3)
+            .filter(|name| **name != ".")
+            .map(|name| name.to_string());
+        region.score = match fields.get(4) {
+            Some(&".") | None => None,
+            Some(score) => Some(
+                score
+                    .parse::<f64>()
+                    .map_err(|e| "Parse Float Error, ".to_string() + &e.to_string())?,
+            ),
+        };
+        if let Some(strand) = fields.get(5) {
+            region.strand = Some(strand.parse::<Strand>()?);
+        }
+        region.system = Some(CoordinateSystem::ZeroHalfOpen);
+        Ok(region)
+    }
+
+    /// Writes the region back as BED columns. A missing name or score is written as `.`, and
+    /// only when a later column has to be written.
+    pub fn to_bed_fields(&self) -> Vec<String> {
+        self.to_bed_fields_with_clamp(false)
+    }
+
+    /// Same as `to_bed_fields`, optionally clamping the score into the 0-1000 range required
+    /// by the BED specification.
+    pub fn to_bed_fields_with_clamp(&self, clamp_score: bool) -> Vec<String> {
+        let mut fields = vec![
+            self.path.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+        ];
+        if self.name.is_some() || self.score.is_some() || self.strand.is_some() {
+            fields.push(self.name.clone().unwrap_or_else(|| ".".to_string()));
+        }
+        if self.score.is_some() || self.strand.is_some() {
+            fields.push(match self.score {
+                Some(score) if clamp_score => score.clamp(0.0, 1000.0).to_string(),
+                Some(score) => score.to_string(),
+                None => ".".to_string(),
+            });
+        }
+        if let Some(strand) = self.strand {
+            fields.push(strand.to_string());
+        }
+        fields
+    }
+
+    /// Builds a region from already split GFF columns. GFF is 1-based closed, so the start
+    /// is shifted to the crate's 0-based half-open convention. Positions must be plain
+    /// integers, see `parse_integer_coordinate`.
+    pub fn from_gff_fields(fields: &[&str]) -> Result<Self, Box<dyn Error>> {
+        if fields.len() < 7 {
+            return Err("GFF record requires at least 7 fields".into());
+        }
+        let start_u64 = parse_integer_coordinate(fields[3])?;
+        let end_u64 = parse_integer_coordinate(fields[4])?;
+        if start_u64 == 0 {
+            return Err("GFF start position must be 1-based".into());
+        }
+        let mut region = StringRegion::new_inner(fields[0].to_string(), start_u64 - 1, end_u64);
+        region.strand = Some(fields[6].parse::<Strand>()?);
+        region.system = Some(CoordinateSystem::ZeroHalfOpen);
+        Ok(region)
+    }
+
+    /// Builds a region from a path and `(start, end)` without going through a string;
+    /// `start > end` yields an inverted region.
+    pub fn from_pair(path: impl Into<String>, coords: (u64, u64)) -> Self {
+        StringRegion::new_inner(path.into(), coords.0, coords.1)
+    }
+
+    /// Same as `from_pair`, taking `(path, start, end)`.
+    pub fn from_triple<S: Into<String>>((path, start, end): (S, u64, u64)) -> Self {
+        StringRegion::new_inner(path.into(), start, end)
     }
 
     pub fn new_inner(path: String, start_u64: u64, end_u64: u64) -> Self {
         if start_u64 > end_u64 {
             StringRegion {
-                path: path.to_string(),
+                path,
                 start: end_u64,
                 end: start_u64,
-                inverted: true
+                inverted: true,
+                strand: None,
+                name: None,
+                score: None,
+                system: None,
             }
         } else {
             StringRegion {
-                path: path.to_string(),
+                path,
                 start: start_u64,
                 end: end_u64,
-                inverted: false
+                inverted: false,
+                strand: None,
+                name: None,
+                score: None,
+                system: None,
             }
         }
     }
 
-    pub fn uuid(&self) -> String {
-        return format!("{}", self);
+    /// The overlapping part of two regions on the same path, or `None` if they do not share a
+    /// base. The result keeps the orientation, strand and annotations of `self`.
+    pub fn intersect(&self, other: &StringRegion) -> Option<StringRegion> {
+        self.intersect_by(other, |a, b| a == b)
     }
-}
 
-#[derive(Clone, PartialEq, Debug)]
-pub struct Region {
-    ref_id: u64,
-    start: u64,
-    end: u64,
-}
+    /// Clips a feature to `bounds`, e.g. the viewed window; `intersect` under a name stating
+    /// the asymmetric intent. The orientation of `self` is kept and `None` is returned when
+    /// nothing remains.
+    pub fn trim_to(&self, bounds: &StringRegion) -> Option<StringRegion> {
+        self.intersect(bounds)
+    }
 
-impl Region {
-    /// Creates new region. `ref_id` is 0-based, `start-end` is 0-based half-open interval.
-    pub fn new(ref_id: u64, start: u64, end: u64) -> Region {
-        assert!(
-            start <= end,
-            "Region: start should not be greater than end ({} > {})",
-            start,
-            end
-        );
-        Region { ref_id, start, end }
+    /// Trims every feature to `bounds` in place, dropping those left empty.
+    pub fn trim_all(features: &mut Vec<StringRegion>, bounds: &StringRegion) {
+        *features = features.iter().filter_map(|f| f.trim_to(bounds)).collect();
     }
 
-    pub fn convert<F>(
-        path: &StringRegion,
-        to_id: F,
-    ) -> std::result::Result<Self, Box<dyn std::error::Error>>
+    /// Same as `intersect`, deciding whether the paths match with `same_path`, e.g. to treat
+    /// `chr1` and `1` as the same contig.
+    pub fn intersect_by<F>(&self, other: &StringRegion, same_path: F) -> Option<StringRegion>
     where
-        F: Fn(&str) -> Option<u64>,
+        F: Fn(&str, &str) -> bool,
     {
-        Ok(Region {
-            ref_id: to_id(&path.path).ok_or("Error: the reference id is not recognized.")?,
-            start: path.start,
-            end: path.end,
-        })
+        self.intersect_allow_empty_by(other, same_path)
+            .filter(|region| region.start < region.end)
     }
 
-    pub fn parse<F>(path: &str, to_id: F) -> std::result::Result<Self, Box<dyn std::error::Error>>
+    /// Same as `intersect`, but regions that only touch yield a zero-length region.
+    pub fn intersect_allow_empty(&self, other: &StringRegion) -> Option<StringRegion> {
+        self.intersect_allow_empty_by(other, |a, b| a == b)
+    }
+
+    fn intersect_allow_empty_by<F>(
+        &self,
+        other: &StringRegion,
+        same_path: F,
+    ) -> Option<StringRegion>
     where
-        F: Fn(&str) -> Option<u64>,
+        F: Fn(&str, &str) -> bool,
     {
-        let re = Regex::new(r"^(.+):(\d*)-?(\d*)$").unwrap();
-        let caps = re.captures(path).ok_or("Invalid genomic range")?;
-        let path = caps
-            .get(1)
-            .and_then(|t| Some(t.as_str()))
-            .ok_or("Parse Path Error")?;
-        let start = caps
-            .get(2)
-            .and_then(|t| t.as_str().parse::<u64>().ok())
-            .ok_or("Error: the reference start is not recognized.")?;
-        let end = caps
-            .get(3)
-            .and_then(|t| t.as_str().parse::<u64>().ok())
-            .ok_or("Error: the reference end is not recognized.")?;
+        if !same_path(&self.path, &other.path) {
+            return None;
+        }
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start > end {
+            return None;
+        }
+        let mut region = self.clone();
+        region.start = start;
+        region.end = end;
+        Some(region)
+    }
 
-        return Ok(Region {
-            ref_id: to_id(path).ok_or("Error: the reference id is not recognized.")?,
-            start: start,
-            end: end,
-        });
+    /// Merges two regions on the same path that overlap or are adjacent. Unlike `overlaps`,
+    /// adjacency (`self.end == other.start` or the reverse) is enough to merge. The result keeps
+    /// the orientation, strand and annotations of `self`.
+    pub fn union(&self, other: &StringRegion) -> Option<StringRegion> {
+        if !self.touches(other) {
+            return None;
+        }
+        self.hull(other)
     }
 
-    pub fn ref_id(&self) -> u64 {
-        self.ref_id
+    /// Whether the two regions share a boundary on the same path (`self.end == other.start` or
+    /// the reverse) without sharing a base.
+    pub fn is_adjacent(&self, other: &StringRegion) -> bool {
+        self.path == other.path && (self.end == other.start || other.end == self.start)
     }
 
-    pub fn start(&self) -> u64 {
-        self.start
+    /// Whether the two regions overlap or are adjacent on the same path. This is exactly the
+    /// condition under which `union` returns `Some`; for non-empty regions it equals
+    /// `is_adjacent` or a non-empty `intersect`.
+    pub fn touches(&self, other: &StringRegion) -> bool {
+        self.path == other.path && self.start.max(other.start) <= self.end.min(other.end)
+    }
+
+    /// The smallest region enclosing both, even across a gap, if the paths match. The result
+    /// keeps the orientation, strand and annotations of `self`.
+    pub fn hull(&self, other: &StringRegion) -> Option<StringRegion> {
+        if self.path != other.path {
+            return None;
+        }
+        let mut region = self.clone();
+        region.start = self.start.min(other.start);
+        region.end = self.end.max(other.end);
+        Some(region)
+    }
+
+    /// Removes `other` from `self`, leaving zero, one or two fragments in ascending order.
+    /// Fragments are never empty, so an empty `self` leaves none, as for `Region::subtract`.
+    /// Each fragment keeps the orientation, strand and annotations of `self`.
+    pub fn subtract(&self, other: &StringRegion) -> Vec<StringRegion> {
+        if self.start == self.end {
+            return vec![];
+        }
+        if self.intersect(other).is_none() {
+            return vec![self.clone()];
+        }
+        let mut fragments = vec![];
+        for (start, end) in [(self.start, other.start), (other.end, self.end)] {
+            let (start, end) = (start.max(self.start), end.min(self.end));
+            if start < end {
+                let mut fragment = self.clone();
+                fragment.start = start;
+                fragment.end = end;
+                fragments.push(fragment);
+            }
+        }
+        fragments
+    }
+
+    /// Whether `pos` lies in `[start, end)` on `path`. Orientation is ignored, so an inverted
+    /// region contains the same positions as its forward counterpart.
+    pub fn contains_pos(&self, path: &str, pos: u64) -> bool {
+        self.contains_pos_by(path, pos, |a, b| a == b)
+    }
+
+    /// Same as `contains_pos`, deciding whether the paths match with `same_path`, e.g.
+    /// `eq_ignore_chr_prefix`.
+    pub fn contains_pos_by<F>(&self, path: &str, pos: u64, same_path: F) -> bool
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        same_path(&self.path, path) && self.start <= pos && pos < self.end
+    }
+
+    /// Same as `contains_pos`, reading `end` under `closedness`.
+    pub fn contains_pos_with(&self, path: &str, pos: u64, closedness: Closedness) -> bool {
+        self.path == path && self.start <= pos && pos < closedness.exclusive_end(self.end)
+    }
+
+    /// Whether the two regions share a base on the same path, reading the `end` of both
+    /// under `closedness`. Orientation is ignored.
+    pub fn overlaps_with(&self, other: &StringRegion, closedness: Closedness) -> bool {
+        self.path == other.path
+            && self.start.max(other.start)
+                < closedness
+                    .exclusive_end(self.end)
+                    .min(closedness.exclusive_end(other.end))
+    }
+
+    /// Whether `other` lies entirely within `self` on the same path. Either region may be
+    /// inverted.
+    pub fn contains_region(&self, other: &StringRegion) -> bool {
+        self.contains_region_by(other, |a, b| a == b)
+    }
+
+    /// Same as `contains_region`, deciding whether the paths match with `same_path`.
+    pub fn contains_region_by<F>(&self, other: &StringRegion, same_path: F) -> bool
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        same_path(&self.path, &other.path) && self.start <= other.start && other.end <= self.end
+    }
+
+    /// Offset of `pos` from the biological start of the region: counted from `start` on the
+    /// forward strand and back from the last base on the reverse strand (see `upstream`).
+    /// `None` when `pos` lies outside the region.
+    pub fn offset_of(&self, pos: u64) -> Option<u64> {
+        if pos < self.start || pos >= self.end {
+            return None;
+        }
+        if self.is_reverse() {
+            Some(self.end - 1 - pos)
+        } else {
+            Some(pos - self.start)
+        }
+    }
+
+    /// The inverse of `offset_of`: the position `offset` bases from the biological start, or
+    /// `None` past the end of the region.
+    pub fn position_at_offset(&self, offset: u64) -> Option<u64> {
+        if offset >= self.interval() {
+            return None;
+        }
+        if self.is_reverse() {
+            Some(self.end - 1 - offset)
+        } else {
+            Some(self.start + offset)
+        }
+    }
+
+    /// The base a fraction `f` of the way through the region from its biological start (see
+    /// `offset_of`): the offset is `floor(f * len)`, capped at the last base so `f = 1.0` is
+    /// the biological end. `None` for NaN, `f` outside `[0, 1]` or an empty region.
+    pub fn position_at_fraction(&self, f: f64) -> Option<u64> {
+        if !(0.0..=1.0).contains(&f) || self.interval() == 0 {
+            return None;
+        }
+        let offset = ((f * self.interval() as f64).floor() as u64).min(self.interval() - 1);
+        self.position_at_offset(offset)
+    }
+
+    /// The `n - 1` internal boundaries between the bins of `split_evenly(n)`, in the same
+    /// order, so leading bins take the remainder and an inverted region is cut from its highest
+    /// coordinate down. Fewer are returned when `n` exceeds the length.
+    pub fn quantile_breakpoints(&self, n: usize) -> Vec<u64> {
+        let bins = self.split_evenly(n);
+        bins.iter()
+            .take(bins.len().saturating_sub(1))
+            .map(|bin| if self.inverted { bin.start } else { bin.end })
+            .collect()
+    }
+
+    /// Half-open offsets covered by `sub` in the coordinates of `offset_of`, or `None` unless
+    /// `sub` lies entirely within `self` on the same path.
+    pub fn project(&self, sub: &StringRegion) -> Option<Range<u64>> {
+        if !self.contains_region(sub) {
+            return None;
+        }
+        if self.is_reverse() {
+            Some(self.end - sub.end..self.end - sub.start)
+        } else {
+            Some(sub.start - self.start..sub.end - self.start)
+        }
+    }
+
+    /// Lifts a region given in coordinates local to `parent` (offset 0 at its biological start,
+    /// as in `offset_of`) back to genomic coordinates on `parent`'s path. The path of `self` is
+    /// ignored. On a reverse parent the orientation of the result is flipped. `None` when
+    /// `self` extends past the end of `parent`.
+    pub fn rebase_onto(&self, parent: &StringRegion) -> Option<StringRegion> {
+        if self.end > parent.interval() {
+            return None;
+        }
+        let mut region = self.clone();
+        region.path = parent.path.clone();
+        if parent.is_reverse() {
+            region.start = parent.end - self.end;
+            region.end = parent.end - self.start;
+            region.inverted = !self.inverted;
+        } else {
+            region.start = parent.start + self.start;
+            region.end = parent.start + self.end;
+        }
+        Some(region)
+    }
+
+    /// The inverse of `rebase_onto`: `self` in coordinates local to `parent`, with the path set
+    /// to `parent`'s `Display` form (e.g. the name of a sequence extracted with
+    /// `samtools faidx`). `None` unless `self` lies within `parent`.
+    pub fn localize_within(&self, parent: &StringRegion) -> Option<StringRegion> {
+        let local = parent.project(self)?;
+        let mut region = self.clone();
+        region.path = parent.uuid();
+        region.start = local.start;
+        region.end = local.end;
+        if parent.is_reverse() {
+            region.inverted = !self.inverted;
+        }
+        Some(region)
+    }
+
+    /// Number of bases between the two regions on the same path; 0 when they overlap or
+    /// touch. Orientation is ignored.
+    pub fn gap(&self, other: &StringRegion) -> Option<u64> {
+        if self.path != other.path {
+            return None;
+        }
+        Some(
+            self.start
+                .max(other.start)
+                .saturating_sub(self.end.min(other.end)),
+        )
+    }
+
+    /// Signed `gap`: positive when `other` lies at higher coordinates than `self`, negative
+    /// when lower. Overlapping and touching regions are both at distance 0. `None` for
+    /// different paths or a gap that does not fit in an `i64`.
+    pub fn distance(&self, other: &StringRegion) -> Option<i64> {
+        signed_gap(self.gap(other)?, other.start >= self.end)
+    }
+
+    /// Where `self` lies relative to `other` (e.g. a variant against a gene), with the direction
+    /// taken from `other`'s strand. `None` when the paths differ or `other` has no `+`/`-`
+    /// strand. Regions that share a boundary are at distance 0 rather than overlapping.
+    pub fn relative_position(&self, other: &StringRegion) -> Option<RelativePosition> {
+        let reverse = match other.strand {
+            Some(Strand::Forward) => false,
+            Some(Strand::Reverse) => true,
+            Some(Strand::Unknown) | None => return None,
+        };
+        let gap = self.gap(other)?;
+        if self.start.max(other.start) < self.end.min(other.end) {
+            Some(RelativePosition::Overlapping)
+        } else if (self.end <= other.start) != reverse {
+            Some(RelativePosition::Upstream(gap))
+        } else {
+            Some(RelativePosition::Downstream(gap))
+        }
+    }
+
+    /// Whether `self` lies upstream of `other` on `other`'s strand; `false` when they overlap.
+    /// `None` in the cases described in `relative_position`.
+    pub fn is_upstream_of(&self, other: &StringRegion) -> Option<bool> {
+        self.relative_position(other)
+            .map(|position| matches!(position, RelativePosition::Upstream(_)))
+    }
+
+    /// The mirror of `is_upstream_of`.
+    pub fn is_downstream_of(&self, other: &StringRegion) -> Option<bool> {
+        self.relative_position(other)
+            .map(|position| matches!(position, RelativePosition::Downstream(_)))
+    }
+
+    /// Splits into `n` bins as `Region::split_evenly` does. Bins keep the orientation, strand
+    /// and annotations of `self`; for an inverted region they are emitted from the highest
+    /// coordinate down so bin 0 is the biological start and gets the extra base first.
+    pub fn split_evenly(&self, n: usize) -> Vec<StringRegion> {
+        let sizes = even_sizes(self.interval(), n);
+        let mut bins = Vec::with_capacity(sizes.len());
+        let mut cursor = if self.inverted { self.end } else { self.start };
+        for size in sizes {
+            let mut bin = self.clone();
+            if self.inverted {
+                bin.start = cursor - size;
+                bin.end = cursor;
+                cursor -= size;
+            } else {
+                bin.start = cursor;
+                bin.end = cursor + size;
+                cursor += size;
+            }
+            bins.push(bin);
+        }
+        bins
+    }
+
+    /// Windows of `size` bases every `step` bases, as `Region::windows`. Windows are emitted
+    /// in ascending coordinate order and keep the orientation, strand and annotations of
+    /// `self`.
+    pub fn windows(&self, size: u64, step: u64) -> Result<StringWindows<'_>, RegionParseError> {
+        Ok(StringWindows {
+            region: self,
+            bounds: WindowBounds::new(self.start, self.end, size, step, false)?,
+        })
+    }
+
+    /// Same as `windows`, also emitting the trailing windows clipped at the end.
+    pub fn windows_partial(
+        &self,
+        size: u64,
+        step: u64,
+    ) -> Result<StringWindows<'_>, RegionParseError> {
+        Ok(StringWindows {
+            region: self,
+            bounds: WindowBounds::new(self.start, self.end, size, step, true)?,
+        })
+    }
+
+    /// Half-open range `(first, last)` of the indices of the genome-wide bins
+    /// `[k * bin_size, (k + 1) * bin_size)` the region touches. Empty for an empty region.
+    ///
+    /// Panics if `bin_size` is 0.
+    pub fn bin_index_range(&self, bin_size: u64) -> (u64, u64) {
+        assert!(bin_size > 0, "StringRegion: bin size should be positive");
+        let first = self.start / bin_size;
+        if self.start == self.end {
+            return (first, first);
+        }
+        (first, self.end.div_ceil(bin_size))
+    }
+
+    /// Yields the genome-wide bins of `bin_size` clipped to the region, so the first and last
+    /// tiles may be partial. Tiles keep the orientation, strand and annotations of `self`.
+    /// `bin_size` of 0 is an error.
+    pub fn tiles_aligned(
+        &self,
+        bin_size: u64,
+    ) -> Result<impl Iterator<Item = StringRegion> + '_, RegionParseError> {
+        if bin_size == 0 {
+            return Err(RegionParseError::InvalidPosition(
+                "bin size must be positive".to_string(),
+            ));
+        }
+        let (first, last) = self.bin_index_range(bin_size);
+        Ok((first..last).map(move |k| {
+            let mut tile = self.clone();
+            tile.start = self.start.max(k * bin_size);
+            tile.end = self.end.min((k + 1).saturating_mul(bin_size));
+            tile
+        }))
+    }
+
+    /// Snaps both ends to multiples of `bin`, as `Region::align_to`. The orientation, strand
+    /// and annotations are preserved.
+    pub fn align_to(
+        &self,
+        bin: u64,
+        mode: AlignMode,
+    ) -> Result<Option<StringRegion>, RegionParseError> {
+        Ok(
+            align_bounds(self.start, self.end, bin, mode)?.map(|(start, end)| {
+                let mut region = self.clone();
+                region.start = start;
+                region.end = end;
+                region
+            }),
+        )
+    }
+
+    /// Same as `align_to`, also clipping `end` at the contig length.
+    pub fn align_to_within(
+        &self,
+        bin: u64,
+        mode: AlignMode,
+        sizes: &ChromSizes,
+    ) -> Result<Option<StringRegion>, RegionParseError> {
+        let contig_len = sizes.require(&self.path)?;
+        Ok(self.align_to(bin, mode)?.and_then(|mut region| {
+            region.end = region.end.min(contig_len);
+            if region.start < region.end {
+                Some(region)
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Splits at `pos` into two fragments in biological order (the higher one first for an
+    /// inverted region). `None` unless `start < pos < end`. Fragments keep the orientation,
+    /// strand and annotations of `self`.
+    pub fn split_at(&self, pos: u64) -> Option<(StringRegion, StringRegion)> {
+        let mut fragments = self.split_at_many(&[pos]);
+        if fragments.len() != 2 {
+            return None;
+        }
+        let second = fragments.pop()?;
+        let first = fragments.pop()?;
+        Some((first, second))
+    }
+
+    /// Splits at every breakpoint strictly inside the region, as `Region::split_at_many`.
+    /// Fragments come out in biological order, from the highest coordinate down for an
+    /// inverted region.
+    pub fn split_at_many(&self, positions: &[u64]) -> Vec<StringRegion> {
+        let mut fragments: Vec<StringRegion> = fragment_bounds(self.start, self.end, positions)
+            .into_iter()
+            .map(|(start, end)| {
+                let mut fragment = self.clone();
+                fragment.start = start;
+                fragment.end = end;
+                fragment
+            })
+            .collect();
+        if self.inverted {
+            fragments.reverse();
+        }
+        fragments
+    }
+
+    /// Same as `split_at_many`, failing with `InvalidPosition` for a breakpoint outside
+    /// `[start, end]`.
+    pub fn split_at_many_strict(
+        &self,
+        positions: &[u64],
+    ) -> Result<Vec<StringRegion>, RegionParseError> {
+        check_breakpoints(self.start, self.end, positions)?;
+        Ok(self.split_at_many(positions))
+    }
+
+    pub fn uuid(&self) -> String {
+        format!("{}", self)
+    }
+
+    /// Just the path when the region spans the whole contig `[0, contig_len)`, otherwise the
+    /// same as `Display`.
+    pub fn display_compact(&self, contig_len: Option<u64>) -> String {
+        if self.start == 0 && contig_len == Some(self.end) {
+            self.path.clone()
+        } else {
+            self.uuid()
+        }
+    }
+
+    /// A key identifying the locus regardless of style: `chr` prefix stripped, forward
+    /// orientation, no strand suffix.
+    pub fn canonical(&self) -> String {
+        let (path, start, end) = self.canonical_key();
+        format!("{}:{}-{}", path, start, end)
+    }
+
+    /// The parts of `canonical` before formatting, ordered numerically.
+    fn canonical_key(&self) -> (&str, u64, u64) {
+        let path = self.path.strip_prefix("chr").unwrap_or(&self.path);
+        (path, self.start, self.end)
+    }
+
+    /// Non-fatal problems worth reporting to the user, in a fixed order. `ExceedsContig` is
+    /// only checked when `contig_len` is given.
+    pub fn validate(&self, contig_len: Option<u64>) -> Vec<RegionWarning> {
+        let mut warnings = vec![];
+        if self.start == self.end {
+            warnings.push(RegionWarning::ZeroLength);
+        }
+        if let Some(contig_len) = contig_len.filter(|len| self.end > *len) {
+            warnings.push(RegionWarning::ExceedsContig {
+                end: self.end,
+                contig_len,
+            });
+        }
+        if self.inverted {
+            warnings.push(RegionWarning::Inverted);
+        }
+        warnings
+    }
+}
+
+/// A suspicious but valid region, as reported by `StringRegion::validate`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RegionWarning {
+    /// `start == end`.
+    ZeroLength,
+    /// `end` lies past the end of the contig.
+    ExceedsContig { end: u64, contig_len: u64 },
+    /// The region was written with `start > end`.
+    Inverted,
+}
+
+/// A user-supplied locus that is either a single position (`chr1:1000`) or a range
+/// (`chr1:1000-2000`).
+#[derive(Debug, PartialEq, Clone)]
+pub enum Locus {
+    Point { path: String, pos: u64 },
+    Range(StringRegion),
+}
+
+/// Parses a point or a range. A bare contig name without coordinates is rejected.
+pub fn parse_locus(s: &str) -> Result<Locus, RegionParseError> {
+    if let Ok(region) = StringRegion::new(s) {
+        return Ok(Locus::Range(region));
+    }
+    let region =
+        OptionalRegion::new(s).map_err(|e| RegionParseError::InvalidFormat(e.to_string()))?;
+    match (region.start, region.end) {
+        (Some(pos), None) if !s.ends_with('-') => Ok(Locus::Point {
+            path: region.path,
+            pos,
+        }),
+        _ => Err(RegionParseError::InvalidFormat(s.to_string())),
+    }
+}
+
+/// A region whose path borrows from the parsed input, for hot loops that cannot afford an
+/// allocation per record. Accepts the same forms as `StringRegion::new`, unit words included.
+/// It is the borrowed
+/// counterpart of `StringRegion`; convert with `into_owned` and `StringRegion::as_borrowed`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BorrowedRegion<'a> {
+    pub path: &'a str,
+    pub start: u64,
+    pub end: u64,
+    inverted: bool,
+    strand: Option<Strand>,
+}
+
+impl<'a> BorrowedRegion<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, Box<dyn Error>> {
+        let (input, unit_exp) = split_unit_word(input);
+        let mut fields = input.split_whitespace();
+        if let (Some(path), Some(start), Some(end)) = (fields.next(), fields.next(), fields.next())
+        {
+            return Ok(BorrowedRegion::new_inner(
+                path,
+                parse_scaled_coordinate(start, unit_exp)?,
+                parse_scaled_coordinate(end, unit_exp)?,
+                None,
+            ));
+        }
+        let (body, strand) = match input.rfind(':') {
+            Some(i) => match Strand::from_symbol(&input[i + 1..]) {
+                Some(strand) => (&input[..i], Some(strand)),
+                None => (input, None),
+            },
+            None => (input, None),
+        };
+        let colon = body.rfind(':').ok_or("Invalid genomic range")?;
+        let (path, coords) = (&body[..colon], &body[colon + 1..]);
+        if path.is_empty() {
+            return Err("Parse Path Error".into());
+        }
+        let (start, end) = match coords.find('-') {
+            Some(dash) => (&coords[..dash], &coords[dash + 1..]),
+            None => (coords, ""),
+        };
+        Ok(BorrowedRegion::new_inner(
+            path,
+            parse_scaled_coordinate(start, unit_exp)?,
+            parse_scaled_coordinate(end, unit_exp)?,
+            strand,
+        ))
+    }
+
+    fn new_inner(path: &'a str, start: u64, end: u64, strand: Option<Strand>) -> Self {
+        BorrowedRegion {
+            path,
+            start: start.min(end),
+            end: start.max(end),
+            inverted: start > end,
+            strand,
+        }
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    pub fn strand(&self) -> Option<Strand> {
+        self.strand
+    }
+
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    pub fn into_owned(self) -> StringRegion {
+        let mut region = StringRegion::new_inner(self.path.to_string(), self.start, self.end);
+        region.inverted = self.inverted;
+        region.strand = self.strand;
+        region
+    }
+}
+
+/// Compares two contig names ignoring a leading `chr`, so `chr1` matches `1`.
+pub fn eq_ignore_chr_prefix(a: &str, b: &str) -> bool {
+    a.strip_prefix("chr").unwrap_or(a) == b.strip_prefix("chr").unwrap_or(b)
+}
+
+fn shift_checked(pos: u64, delta: i64) -> Option<u64> {
+    if delta < 0 {
+        pos.checked_sub(delta.unsigned_abs())
+    } else {
+        pos.checked_add(delta as u64)
+    }
+}
+
+fn shift_saturating(pos: u64, delta: i64) -> u64 {
+    if delta < 0 {
+        pos.saturating_sub(delta.unsigned_abs())
+    } else {
+        pos.saturating_add(delta as u64)
+    }
+}
+
+fn shift_error(start: u64, end: u64, delta: i64) -> RegionParseError {
+    RegionParseError::InvalidPosition(format!(
+        "shifting {}-{} by {} is out of bounds",
+        start, end, delta
+    ))
+}
+
+/// `[mid - len / 2, mid + len - len / 2)`, clipped at 0.
+fn centered_bounds(mid: u64, len: u64) -> (u64, u64) {
+    (
+        mid.saturating_sub(len / 2),
+        mid.saturating_add(len - len / 2),
+    )
+}
+
+fn align_bounds(
+    start: u64,
+    end: u64,
+    bin: u64,
+    mode: AlignMode,
+) -> Result<Option<(u64, u64)>, RegionParseError> {
+    if bin == 0 {
+        return Err(RegionParseError::InvalidPosition(
+            "bin size must be positive".to_string(),
+        ));
+    }
+    let down = |pos: u64| pos - pos % bin;
+    let up = |pos: u64| pos.div_ceil(bin).saturating_mul(bin);
+    Ok(match mode {
+        AlignMode::Expand => Some((down(start), up(end))),
+        AlignMode::Shrink => {
+            let (start, end) = (up(start), down(end));
+            if start < end {
+                Some((start, end))
+            } else {
+                None
+            }
+        }
+    })
+}
+
+/// `[start, end)` seen from the other end of a contig of length `len`.
+fn mirror_bounds(start: u64, end: u64, len: u64) -> Result<(u64, u64), RegionParseError> {
+    if end > len {
+        return Err(RegionParseError::InvalidPosition(format!(
+            "{}-{} exceeds the contig length {}",
+            start, end, len
+        )));
+    }
+    Ok((len - end, len - start))
+}
+
+/// Bounds of the fragments of `[start, end)` cut at the breakpoints strictly inside it.
+fn fragment_bounds(start: u64, end: u64, positions: &[u64]) -> Vec<(u64, u64)> {
+    let mut cuts: Vec<u64> = positions
+        .iter()
+        .copied()
+        .filter(|pos| start < *pos && *pos < end)
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    let mut bounds = Vec::with_capacity(cuts.len() + 1);
+    let mut cursor = start;
+    for cut in cuts {
+        bounds.push((cursor, cut));
+        cursor = cut;
+    }
+    if cursor < end {
+        bounds.push((cursor, end));
+    }
+    bounds
+}
+
+fn check_breakpoints(start: u64, end: u64, positions: &[u64]) -> Result<(), RegionParseError> {
+    match positions.iter().find(|pos| **pos < start || end < **pos) {
+        Some(pos) => Err(RegionParseError::InvalidPosition(format!(
+            "breakpoint {} is outside {}-{}",
+            pos, start, end
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Lengths of at most `n` non-empty bins tiling `len`, leading bins one longer.
+fn even_sizes(len: u64, n: usize) -> Vec<u64> {
+    let n = (n as u64).min(len);
+    if n == 0 {
+        return vec![];
+    }
+    let (size, remainder) = (len / n, len % n);
+    (0..n).map(|i| size + u64::from(i < remainder)).collect()
+}
+
+fn signed_gap(gap: u64, downstream: bool) -> Option<i64> {
+    let gap = i64::try_from(gap).ok()?;
+    Some(if downstream { gap } else { -gap })
+}
+
+/// Power of ten for a trailing unit word accepted by `StringRegion::new`.
+fn unit_exponent(unit: &str) -> Option<i64> {
+    [("bp", 0), ("kb", 3), ("mb", 6)]
+        .iter()
+        .find(|(word, _)| unit.eq_ignore_ascii_case(word))
+        .map(|&(_, exp)| exp)
+}
+
+/// Splits a trailing unit word off a region written as `chr:start-end` or as three space
+/// separated fields, returning the rest of the input and the unit's power of ten. Input
+/// without a unit, or tab separated input, is returned unchanged with exponent 0.
+fn split_unit_word(input: &str) -> (&str, i64) {
+    if input.contains('\t') {
+        return (input, 0);
+    }
+    if let Some((body, unit)) = input.trim_end().rsplit_once(char::is_whitespace) {
+        let body = body.trim();
+        let fields = body.split_whitespace().count();
+        if let Some(exp) = unit_exponent(unit).filter(|_| fields == 1 || fields == 3) {
+            return (body, exp);
+        }
+    }
+    (input, 0)
+}
+
+/// Parses an optional regex capture with `parse_coordinate`; an empty match is `None`.
+fn optional_coordinate(m: Option<regex::Match>) -> Result<Option<u64>, RegionParseError> {
+    m.map(|m| m.as_str())
+        .filter(|s| !s.is_empty())
+        .map(parse_coordinate)
+        .transpose()
+}
+
+/// Regex fragment matching the coordinates accepted by `parse_coordinate`.
+const COORDINATE: &str = r"\d[\d,]*(?:\.\d+)?(?:[eE]\+?\d+)?[kKmMgG]?";
+
+/// Parses a coordinate of a machine-written format (BED, GFF, BEDPE, `chrom.sizes`). These
+/// are plain decimal integers by specification, so unlike `parse_coordinate` no commas,
+/// decimals, exponents or suffixes are accepted; errors are `InvalidPosition` in the same
+/// shape as `parse_coordinate`'s.
+pub fn parse_integer_coordinate(s: &str) -> Result<u64, RegionParseError> {
+    let invalid = |reason: &str| RegionParseError::InvalidPosition(format!("{:?}: {}", s, reason));
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid("not a plain integer"));
+    }
+    s.bytes()
+        .try_fold(0u64, |value, b| {
+            value.checked_mul(10)?.checked_add(u64::from(b - b'0'))
+        })
+        .ok_or_else(|| invalid("value too large"))
+}
+
+/// The regex in `cell`, compiled on first use from `pattern` with each `{0}` replaced by
+/// `COORDINATE`.
+fn coordinate_regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(&pattern.replace("{0}", COORDINATE)).unwrap())
+}
+
+/// Parses a coordinate as written by people: plain digits, optionally with thousands commas,
+/// a decimal part, an exponent and a `k`, `M` or `G` suffix (case-insensitive). Each comma
+/// must be followed by a group of exactly three digits, so `1,200` is accepted and `1,2M` is
+/// not, and commas may only appear before the decimal point. The exponent applies to the
+/// decimal number and the suffix multiplies the result, so `1.5e2k` is `150000`. A value that
+/// is not a whole number, such as `1.5` or `1.2345k`, fails with `InvalidPosition`, as does
+/// one past `u64`.
+pub fn parse_coordinate(s: &str) -> Result<u64, RegionParseError> {
+    parse_scaled_coordinate(s, 0)
+}
+
+/// `parse_coordinate` multiplied by `10^unit_exp` before the whole-number check.
+fn parse_scaled_coordinate(s: &str, unit_exp: i64) -> Result<u64, RegionParseError> {
+    let invalid = |reason: &str| RegionParseError::InvalidPosition(format!("{:?}: {}", s, reason));
+    let bytes = s.as_bytes();
+    if bytes.first().is_none_or(|b| !b.is_ascii_digit()) {
+        return Err(invalid("not a number"));
+    }
+    if unit_exp == 0 && bytes.iter().all(u8::is_ascii_digit) {
+        return parse_integer_coordinate(s);
+    }
+    let (body, suffix_exp) = match bytes[bytes.len() - 1].to_ascii_lowercase() {
+        b'k' => (&bytes[..bytes.len() - 1], 3),
+        b'm' => (&bytes[..bytes.len() - 1], 6),
+        b'g' => (&bytes[..bytes.len() - 1], 9),
+        _ => (bytes, 0),
+    };
+    let (mantissa, exp) = match body.iter().position(|&b| b == b'e' || b == b'E') {
+        Some(i) => {
+            let exp = &body[i + 1..];
+            let exp = exp.strip_prefix(b"+").unwrap_or(exp);
+            if exp.is_empty() || !exp.iter().all(u8::is_ascii_digit) {
+                return Err(invalid("malformed exponent"));
+            }
+            let exp = exp
+                .iter()
+                .try_fold(0i64, |exp, &b| {
+                    exp.checked_mul(10)?.checked_add(i64::from(b - b'0'))
+                })
+                .ok_or_else(|| invalid("exponent too large"))?;
+            (&body[..i], exp)
+        }
+        None => (body, 0),
+    };
+    let (integer, fraction) = match mantissa.iter().position(|&b| b == b'.') {
+        Some(i) if i + 1 < mantissa.len() => (&mantissa[..i], &mantissa[i + 1..]),
+        Some(_) => return Err(invalid("missing digits after the decimal point")),
+        None => (mantissa, &[][..]),
+    };
+    if fraction.contains(&b',') {
+        return Err(invalid("commas are only allowed in the integer part"));
+    }
+    // Digits since the last comma, once one has been seen.
+    let mut group: Option<usize> = None;
+    for &b in integer {
+        match b {
+            b',' if group.is_some_and(|n| n != 3) => {
+                return Err(invalid("commas must separate groups of three digits"));
+            }
+            b',' => group = Some(0),
+            b'0'..=b'9' => group = group.map(|n| n + 1),
+            _ => return Err(invalid("not a number")),
+        }
+    }
+    if group.is_some_and(|n| n != 3) {
+        return Err(invalid("commas must separate groups of three digits"));
+    }
+    if !fraction.iter().all(u8::is_ascii_digit) {
+        return Err(invalid("not a number"));
+    }
+    // The value is `significant * 10^scale`; trailing zeros are moved into the scale so that
+    // a whole number written with decimals, like `1.50k`, ends up with a non-negative scale.
+    let too_large = || invalid("value too large");
+    let mut significant = 0u64;
+    let mut zeros = 0u32;
+    for &b in integer.iter().chain(fraction).filter(|&&b| b != b',') {
+        if b == b'0' {
+            zeros += 1;
+        } else {
+            significant = 10u64
+                .checked_pow(zeros + 1)
+                .and_then(|factor| significant.checked_mul(factor))
+                .and_then(|value| value.checked_add(u64::from(b - b'0')))
+                .ok_or_else(too_large)?;
+            zeros = 0;
+        }
+    }
+    if significant == 0 {
+        return Ok(0);
+    }
+    let scale = exp
+        .checked_add(suffix_exp + unit_exp)
+        .and_then(|scale| scale.checked_sub(fraction.len() as i64))
+        .and_then(|scale| scale.checked_add(i64::from(zeros)))
+        .ok_or_else(|| invalid("exponent too large"))?;
+    if scale < 0 {
+        return Err(invalid("not a whole number"));
+    }
+    u32::try_from(scale)
+        .ok()
+        .and_then(|scale| 10u64.checked_pow(scale))
+        .and_then(|factor| significant.checked_mul(factor))
+        .ok_or_else(too_large)
+}
+
+impl AsRef<StringRegion> for StringRegion {
+    fn as_ref(&self) -> &StringRegion {
+        self
+    }
+}
+
+/// A `StringRegion` carrying an arbitrary payload. It derefs to the region, so region methods
+/// and the helpers taking `AsRef<StringRegion>` work on it directly.
+///
+/// Equality and ordering look at the region coordinates `(path, start, end)` only; the payload
+/// is ignored.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RegionWith<T> {
+    pub region: StringRegion,
+    pub data: T,
+}
+
+impl<T> RegionWith<T> {
+    pub fn new(region: StringRegion, data: T) -> Self {
+        RegionWith { region, data }
+    }
+
+    fn key(&self) -> (&str, u64, u64) {
+        (&self.region.path, self.region.start, self.region.end)
+    }
+}
+
+impl RegionWith<Vec<String>> {
+    /// Parses BED columns as `StringRegion::from_bed_fields` does and keeps any columns past
+    /// the sixth as the payload.
+    pub fn from_bed_fields(fields: &[&str]) -> Result<Self, Box<dyn Error>> {
+        let region = StringRegion::from_bed_fields(&fields[..fields.len().min(6)])?;
+        let data = fields.iter().skip(6).map(|t| t.to_string()).collect();
+        Ok(RegionWith { region, data })
+    }
+}
+
+impl<T> Deref for RegionWith<T> {
+    type Target = StringRegion;
+
+    fn deref(&self) -> &StringRegion {
+        &self.region
+    }
+}
+
+impl<T> DerefMut for RegionWith<T> {
+    fn deref_mut(&mut self) -> &mut StringRegion {
+        &mut self.region
+    }
+}
+
+impl<T> AsRef<StringRegion> for RegionWith<T> {
+    fn as_ref(&self) -> &StringRegion {
+        &self.region
+    }
+}
+
+impl<T> PartialEq for RegionWith<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<T> Eq for RegionWith<T> {}
+
+impl<T> PartialOrd for RegionWith<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for RegionWith<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Sorts the regions and merges those on the same path that overlap or touch, combining the
+/// payloads of merged records with `combine`. Merged regions are emitted in forward
+/// orientation without strand, name or score.
+pub fn merge_with<T, F>(mut regions: Vec<RegionWith<T>>, mut combine: F) -> Vec<RegionWith<T>>
+where
+    F: FnMut(T, T) -> T,
+{
+    regions.sort();
+    let mut merged: Vec<RegionWith<T>> = Vec::with_capacity(regions.len());
+    for next in regions {
+        match merged.last_mut() {
+            Some(last) if last.path == next.path && next.start <= last.end => {
+                let end = last.end.max(next.end);
+                let region = StringRegion::new_inner(last.path.clone(), last.start, end);
+                let prev = merged.pop().unwrap();
+                merged.push(RegionWith::new(region, combine(prev.data, next.data)));
+            }
+            _ => {
+                let region =
+                    StringRegion::new_inner(next.region.path, next.region.start, next.region.end);
+                merged.push(RegionWith::new(region, next.data));
+            }
+        }
+    }
+    merged
+}
+
+/// A region followed by `;key=value` tags, as in `chr1:100-200;id=peak_17;fold=8.2`.
+/// Keys are unique and keep their insertion order; values may contain `=` but not `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedRegion {
+    pub region: StringRegion,
+    tags: Vec<(String, String)>,
+}
+
+impl TaggedRegion {
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts = path.split(';');
+        let region = StringRegion::new(parts.next().unwrap_or(""))?;
+        let mut tagged = TaggedRegion {
+            region,
+            tags: vec![],
+        };
+        for part in parts {
+            let (key, value) = part.split_once('=').ok_or_else(|| {
+                RegionParseError::InvalidFormat(format!("tag without '=': {}", part))
+            })?;
+            if tagged.tag(key).is_some() {
+                return Err(
+                    RegionParseError::InvalidFormat(format!("duplicate tag: {}", key)).into(),
+                );
+            }
+            tagged.set_tag(key, value)?;
+        }
+        Ok(tagged)
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets a tag, replacing the value in place if the key already exists.
+    pub fn set_tag(&mut self, key: &str, value: &str) -> Result<(), RegionParseError> {
+        if key.is_empty() || key.contains('=') || key.contains(';') {
+            return Err(RegionParseError::InvalidFormat(format!(
+                "invalid tag key: {}",
+                key
+            )));
+        }
+        if value.contains(';') {
+            return Err(RegionParseError::InvalidFormat(format!(
+                "invalid tag value: {}",
+                value
+            )));
+        }
+        match self.tags.iter_mut().find(|(k, _)| k == key) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((key.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TaggedRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.region)?;
+        for (key, value) in &self.tags {
+            write!(f, ";{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for TaggedRegion {
+    type Target = StringRegion;
+
+    fn deref(&self) -> &StringRegion {
+        &self.region
+    }
+}
+
+impl DerefMut for TaggedRegion {
+    fn deref_mut(&mut self) -> &mut StringRegion {
+        &mut self.region
+    }
+}
+
+impl AsRef<StringRegion> for TaggedRegion {
+    fn as_ref(&self) -> &StringRegion {
+        &self.region
+    }
+}
+
+/// Regions grouped by path and kept sorted by `(start, end)`, for repeated overlap queries.
+/// Insertion keeps the order, so the set can be built from unsorted input. Overlapping regions
+/// are kept apart until `merge_overlaps` is called.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RegionSet {
+    regions: BTreeMap<String, Vec<StringRegion>>,
+    max_len: u64,
+}
+
+impl RegionSet {
+    pub fn new() -> Self {
+        RegionSet::default()
+    }
+
+    /// Adds one region at its sorted position, shifting the later ones; use `extend` or
+    /// `collect` to add many.
+    pub fn insert(&mut self, region: StringRegion) {
+        self.max_len = self.max_len.max(region.interval());
+        let regions = self.regions.entry(region.path.clone()).or_default();
+        let at = regions.partition_point(|r| (r.start, r.end) <= (region.start, region.end));
+        regions.insert(at, region);
+    }
+
+    /// Number of regions stored.
+    pub fn len(&self) -> usize {
+        self.regions.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Collapses, on each path, every run of regions whose gaps are at most `max_gap` bases, as
+    /// `merge_overlaps_within` does for `Region`. Merged regions keep the orientation, strand
+    /// and annotations of the first region of their run.
+    pub fn merge_overlaps(&mut self, max_gap: u64) {
+        for regions in self.regions.values_mut() {
+            let mut merged: Vec<StringRegion> = Vec::with_capacity(regions.len());
+            for region in regions.drain(..) {
+                match merged.last_mut() {
+                    Some(last) if region.start <= last.end.saturating_add(max_gap) => {
+                        last.end = last.end.max(region.end);
+                    }
+                    _ => merged.push(region),
+                }
+            }
+            *regions = merged;
+        }
+        self.max_len = self.iter().map(StringRegion::interval).max().unwrap_or(0);
+    }
+
+    /// Sum of the region lengths. Overlapping bases are counted once per region unless the set
+    /// has been merged.
+    pub fn total_len(&self) -> u64 {
+        self.iter().map(StringRegion::interval).sum()
+    }
+
+    /// The stored regions sharing at least one base with `query`, in order. Candidates are
+    /// located by binary search on `start`, bounded by the longest stored region.
+    pub fn overlapping(&self, query: &StringRegion) -> impl Iterator<Item = &StringRegion> {
+        let (start, end) = (query.start, query.end);
+        let regions = self.regions.get(&query.path).map_or(&[][..], Vec::as_slice);
+        let first = regions.partition_point(|r| r.start.saturating_add(self.max_len) <= start);
+        let last = regions.partition_point(|r| r.start < end).max(first);
+        regions[first..last]
+            .iter()
+            .filter(move |r| r.start.max(start) < r.end.min(end))
+    }
+
+    /// Whether any stored region on `path` contains `pos`.
+    pub fn contains_pos(&self, path: &str, pos: u64) -> bool {
+        let query = StringRegion::new_inner(path.to_string(), pos, pos.saturating_add(1));
+        self.overlapping(&query).next().is_some()
+    }
+
+    /// All regions, ordered by path and then by `(start, end)`.
+    pub fn iter(&self) -> impl Iterator<Item = &StringRegion> {
+        self.regions.values().flatten()
+    }
+
+    /// The regions as a flat `Vec` in the order of `iter`.
+    pub fn into_vec(self) -> Vec<StringRegion> {
+        self.regions.into_values().flatten().collect()
+    }
+}
+
+/// Appends all regions, then sorts each touched path once, so building from unsorted input
+/// costs O(n log n) rather than one `insert` each.
+impl Extend<StringRegion> for RegionSet {
+    fn extend<T: IntoIterator<Item = StringRegion>>(&mut self, iter: T) {
+        let mut touched: HashSet<String> = HashSet::new();
+        for region in iter {
+            self.max_len = self.max_len.max(region.interval());
+            if !touched.contains(&region.path) {
+                touched.insert(region.path.clone());
+            }
+            self.regions
+                .entry(region.path.clone())
+                .or_default()
+                .push(region);
+        }
+        for path in touched {
+            if let Some(regions) = self.regions.get_mut(&path) {
+                regions.sort_by_key(|r| (r.start, r.end));
+            }
+        }
+    }
+}
+
+impl std::iter::FromIterator<StringRegion> for RegionSet {
+    fn from_iter<T: IntoIterator<Item = StringRegion>>(iter: T) -> Self {
+        let mut set = RegionSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl From<RegionSet> for Vec<StringRegion> {
+    fn from(set: RegionSet) -> Self {
+        set.into_vec()
+    }
+}
+
+/// Values keyed by `Region`, the id-based counterpart of `RegionSet` with a payload per
+/// interval. Each `ref_id` is stored in the same implicit interval tree as `RegionIndex`, so
+/// `get_overlapping` takes O(log n + k) however long the stored regions are. The tree is
+/// rebuilt when entries are added: `insert` costs O(n) for its reference, while `extend` and
+/// `collect` rebuild each touched reference once, so prefer them for bulk loading.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegionMap<V> {
+    contigs: BTreeMap<u64, IndexedContig<Region, V>>,
+}
+
+impl<V> Default for RegionMap<V> {
+    fn default() -> Self {
+        RegionMap {
+            contigs: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V> RegionMap<V> {
+    pub fn new() -> Self {
+        RegionMap::default()
+    }
+
+    /// Adds `value` for `region`. Equal regions may carry several values; they are kept in
+    /// insertion order.
+    pub fn insert(&mut self, region: Region, value: V) {
+        self.extend(std::iter::once((region, value)));
+    }
+
+    /// Number of entries stored.
+    pub fn len(&self) -> usize {
+        self.contigs
+            .values()
+            .map(|contig| contig.entries.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contigs.is_empty()
+    }
+
+    /// The entries whose region shares at least one base with `query`, ordered by
+    /// `(start, end)`.
+    pub fn get_overlapping(&self, query: &Region) -> Vec<(&Region, &V)> {
+        self.contigs.get(&query.ref_id).map_or(vec![], |contig| {
+            contig
+                .overlapping(query.start, query.end)
+                .into_iter()
+                .map(|i| {
+                    let (region, value) = &contig.entries[i];
+                    (region, value)
+                })
+                .collect()
+        })
+    }
+
+    /// All entries, ordered by `ref_id` and then by `(start, end)`.
+    pub fn iter(&self) -> impl Iterator<Item = (&Region, &V)> {
+        self.contigs
+            .values()
+            .flat_map(|contig| contig.entries.iter().map(|(r, v)| (r, v)))
+    }
+}
+
+impl<V> Extend<(Region, V)> for RegionMap<V> {
+    fn extend<T: IntoIterator<Item = (Region, V)>>(&mut self, iter: T) {
+        let mut added: BTreeMap<u64, Vec<(Region, V)>> = BTreeMap::new();
+        for entry in iter {
+            added.entry(entry.0.ref_id).or_default().push(entry);
+        }
+        for (ref_id, mut entries) in added {
+            if let Some(contig) = self.contigs.remove(&ref_id) {
+                entries.splice(0..0, contig.entries);
+            }
+            self.contigs.insert(ref_id, IndexedContig::new(entries));
+        }
+    }
+}
+
+impl<V> std::iter::FromIterator<(Region, V)> for RegionMap<V> {
+    fn from_iter<T: IntoIterator<Item = (Region, V)>>(iter: T) -> Self {
+        let mut map = RegionMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Static overlap index over `(StringRegion, T)` pairs. Each path is stored as an implicit
+/// augmented interval tree (the `cgranges` layout: the entries sorted by start, with the
+/// maximum end of every subtree kept alongside), so building takes O(n log n) and `query`
+/// O(log n + k). `count` uses the sorted starts and ends instead (the BITS algorithm) and
+/// never visits the hits.
+#[derive(Debug, Clone)]
+pub struct RegionIndex<T> {
+    contigs: HashMap<String, IndexedContig<StringRegion, T>>,
+}
+
+/// The entries of one reference in `cgranges` layout, shared by `RegionIndex` and
+/// `RegionMap`.
+#[derive(Debug, PartialEq, Clone)]
+struct IndexedContig<R, T> {
+    entries: Vec<(R, T)>,
+    max_end: Vec<u64>,
+    max_level: u32,
+    starts: Vec<u64>,
+    ends: Vec<u64>,
+}
+
+impl<R: AsRange, T> IndexedContig<R, T> {
+    /// Sorts `entries` by `(start, end)`, keeping equal regions in their given order.
+    fn new(mut entries: Vec<(R, T)>) -> Self {
+        entries.sort_by_key(|(region, _)| region.bounds());
+        let n = entries.len();
+        let mut max_end: Vec<u64> = entries
+            .iter()
+            .map(|(region, _)| region.bounds().1)
+            .collect();
+        let mut max_level = 0;
+        let (mut last_i, mut last) = (0, 0);
+        for i in (0..n).step_by(2) {
+            last_i = i;
+            last = max_end[i];
+        }
+        let mut k = 1;
+        while 1 << k <= n {
+            let x = 1 << (k - 1);
+            for i in ((x << 1) - 1..n).step_by(x << 2) {
+                let right = if i + x < n { max_end[i + x] } else { last };
+                max_end[i] = max_end[i].max(max_end[i - x]).max(right);
+            }
+            if (last_i >> k) & 1 == 0 {
+                last_i += x;
+            }
+            if last_i < n {
+                last = last.max(max_end[last_i]);
+            }
+            max_level = k;
+            k += 1;
+        }
+        let non_empty = entries
+            .iter()
+            .map(|(region, _)| region.bounds())
+            .filter(|(start, end)| start < end);
+        let starts: Vec<u64> = non_empty.clone().map(|(start, _)| start).collect();
+        let mut ends: Vec<u64> = non_empty.map(|(_, end)| end).collect();
+        ends.sort_unstable();
+        IndexedContig {
+            entries,
+            max_end,
+            max_level,
+            starts,
+            ends,
+        }
+    }
+
+    /// Indices of the entries overlapping `[start, end)`, in ascending order of start.
+    fn overlapping(&self, start: u64, end: u64) -> Vec<usize> {
+        let n = self.entries.len();
+        let mut hits = vec![];
+        let overlaps = |i: usize| {
+            let (s, e) = self.entries[i].0.bounds();
+            s.max(start) < e.min(end)
+        };
+        // (level, node, whether the left subtree has been visited)
+        let mut stack = vec![(self.max_level, (1usize << self.max_level) - 1, false)];
+        while let Some((k, x, left_done)) = stack.pop() {
+            if k <= 3 {
+                let i0 = x >> k << k;
+                let i1 = (i0 + (1 << (k + 1)) - 1).min(n);
+                for i in i0..i1 {
+                    if self.entries[i].0.bounds().0 >= end {
+                        break;
+                    }
+                    if overlaps(i) {
+                        hits.push(i);
+                    }
+                }
+            } else if !left_done {
+                let left = x - (1 << (k - 1));
+                stack.push((k, x, true));
+                if left >= n || self.max_end[left] > start {
+                    stack.push((k - 1, left, false));
+                }
+            } else if x < n && self.entries[x].0.bounds().0 < end {
+                if overlaps(x) {
+                    hits.push(x);
+                }
+                stack.push((k - 1, x + (1 << (k - 1)), false));
+            }
+        }
+        hits
+    }
+
+    fn count(&self, start: u64, end: u64) -> usize {
+        if start >= end {
+            return 0;
+        }
+        let started = self.starts.partition_point(|&s| s < end);
+        let finished = self.ends.partition_point(|&e| e <= start);
+        started - finished
+    }
+}
+
+impl<T> RegionIndex<T> {
+    pub fn new(entries: Vec<(StringRegion, T)>) -> Self {
+        let mut by_path: HashMap<String, Vec<(StringRegion, T)>> = HashMap::new();
+        for entry in entries {
+            by_path.entry(entry.0.path.clone()).or_default().push(entry);
+        }
+        RegionIndex {
+            contigs: by_path
+                .into_iter()
+                .map(|(path, entries)| (path, IndexedContig::new(entries)))
+                .collect(),
+        }
+    }
+
+    /// Number of indexed entries.
+    pub fn len(&self) -> usize {
+        self.contigs
+            .values()
+            .map(|contig| contig.entries.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contigs.is_empty()
+    }
+
+    /// The entries sharing at least one base with `region`, in ascending order of start.
+    /// Orientation is ignored.
+    pub fn query(&self, region: &StringRegion) -> impl Iterator<Item = &(StringRegion, T)> {
+        self.query_bounds(&region.path, region.start, region.end)
+    }
+
+    /// Same as `query(region).count()`, in O(log n) regardless of the number of hits.
+    pub fn count(&self, region: &StringRegion) -> usize {
+        self.contigs
+            .get(&region.path)
+            .map_or(0, |contig| contig.count(region.start, region.end))
+    }
+
+    /// The entries containing `pos` on `path`.
+    pub fn query_pos(&self, path: &str, pos: u64) -> impl Iterator<Item = &(StringRegion, T)> {
+        self.query_bounds(path, pos, pos.saturating_add(1))
+    }
+
+    fn query_bounds(
+        &self,
+        path: &str,
+        start: u64,
+        end: u64,
+    ) -> impl Iterator<Item = &(StringRegion, T)> {
+        let (entries, hits) = match self.contigs.get(path) {
+            Some(contig) => (&contig.entries[..], contig.overlapping(start, end)),
+            None => (&[][..], vec![]),
+        };
+        hits.into_iter().map(move |i| &entries[i])
+    }
+}
+
+impl RegionIndex<()> {
+    /// An index over plain regions.
+    pub fn from_regions(regions: Vec<StringRegion>) -> Self {
+        RegionIndex::new(regions.into_iter().map(|region| (region, ())).collect())
+    }
+}
+
+impl<T> std::iter::FromIterator<(StringRegion, T)> for RegionIndex<T> {
+    fn from_iter<I: IntoIterator<Item = (StringRegion, T)>>(iter: I) -> Self {
+        RegionIndex::new(iter.into_iter().collect())
+    }
+}
+
+/// Bounding interval of the regions on each path, bridging any gaps between them. Inverted
+/// inputs contribute their normalized bounds, and every result is in forward orientation
+/// without strand or annotations.
+pub fn envelope<'a>(
+    regions: impl Iterator<Item = &'a StringRegion>,
+) -> HashMap<String, StringRegion> {
+    let mut envelopes: HashMap<String, StringRegion> = HashMap::new();
+    for region in regions {
+        envelopes
+            .entry(region.path.clone())
+            .and_modify(|e| {
+                e.start = e.start.min(region.start);
+                e.end = e.end.max(region.end);
+            })
+            .or_insert_with(|| {
+                StringRegion::new_inner(region.path.clone(), region.start, region.end)
+            });
+    }
+    envelopes
+}
+
+/// Groups regions by path, with paths in order of first appearance. Each group keeps the
+/// input order, or is sorted by `(start, end)` when `sort` is set.
+pub fn partition_by_path(
+    regions: Vec<StringRegion>,
+    sort: bool,
+) -> Vec<(String, Vec<StringRegion>)> {
+    let mut groups: Vec<(String, Vec<StringRegion>)> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for region in regions {
+        let i = *index.entry(region.path.clone()).or_insert_with(|| {
+            groups.push((region.path.clone(), vec![]));
+            groups.len() - 1
+        });
+        groups[i].1.push(region);
+    }
+    if sort {
+        for (_, group) in groups.iter_mut() {
+            group.sort_by_key(|r| (r.start, r.end));
+        }
+    }
+    groups
+}
+
+/// Borrowing counterpart of `partition_by_path` for input whose regions are already grouped
+/// by path, e.g. sorted: splits it into the runs of each path without cloning. A path that
+/// shows up again after another one fails with `RegionError::Unsorted` and its index.
+pub fn group_by_path(
+    regions: &[StringRegion],
+) -> Result<Vec<(&str, &[StringRegion])>, RegionError> {
+    let mut groups: Vec<(&str, &[StringRegion])> = vec![];
+    let mut seen = HashSet::new();
+    let mut start = 0;
+    for i in 1..=regions.len() {
+        if i < regions.len() && regions[i].path == regions[start].path {
+            continue;
+        }
+        let path = regions[start].path.as_str();
+        if !seen.insert(path) {
+            return Err(RegionError::Unsorted {
+                input: 0,
+                index: start,
+            });
+        }
+        groups.push((path, &regions[start..i]));
+        start = i;
+    }
+    Ok(groups)
+}
+
+/// Length summary of a set of regions, as returned by `region_stats`. The optional fields are
+/// `None` for an empty set.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RegionStats {
+    pub count: usize,
+    /// Sum of the lengths, counting overlapping bases once per region; see
+    /// `total_len_merged`.
+    pub total_len: u64,
+    pub min_len: Option<u64>,
+    pub max_len: Option<u64>,
+    pub mean_len: Option<f64>,
+    /// The middle length, or the mean of the two middle ones for an even count.
+    pub median_len: Option<f64>,
+    /// The largest length `L` such that regions of length at least `L` cover half of
+    /// `total_len`.
+    pub n50: Option<u64>,
+}
+
+/// Count and length statistics of `regions`.
+pub fn region_stats<'a>(regions: impl Iterator<Item = &'a StringRegion>) -> RegionStats {
+    let mut lens: Vec<u64> = regions.map(StringRegion::interval).collect();
+    if lens.is_empty() {
+        return RegionStats::default();
+    }
+    lens.sort_unstable();
+    let count = lens.len();
+    let total_len: u64 = lens.iter().sum();
+    let median_len = if count % 2 == 1 {
+        lens[count / 2] as f64
+    } else {
+        (lens[count / 2 - 1] as f64 + lens[count / 2] as f64) / 2.0
+    };
+    let mut covered = 0;
+    let n50 = lens.iter().rev().copied().find(|&len| {
+        covered += len;
+        covered.saturating_mul(2) >= total_len
+    });
+    RegionStats {
+        count,
+        total_len,
+        min_len: lens.first().copied(),
+        max_len: lens.last().copied(),
+        mean_len: Some(total_len as f64 / count as f64),
+        median_len: Some(median_len),
+        n50,
+    }
+}
+
+/// Number of bases covered by `regions`, counting overlapping bases once.
+pub fn total_len_merged<'a>(regions: impl Iterator<Item = &'a StringRegion>) -> u64 {
+    let mut regions: Vec<StringRegion> = regions.cloned().collect();
+    merge_overlaps(&mut regions, 0);
+    regions.iter().map(StringRegion::interval).sum()
+}
+
+/// Sorts `regions` by `(path, start)` and collapses, in place, every run of regions on the
+/// same path whose gaps are at most `max_gap` bases; `max_gap = 0` merges overlapping and
+/// adjacent ones. Only the coordinates are kept: every result is in forward orientation
+/// without strand or annotations.
+pub fn merge_overlaps(regions: &mut Vec<StringRegion>, max_gap: u64) {
+    regions.sort_unstable_by(|a, b| (&a.path, a.start, a.end).cmp(&(&b.path, b.start, b.end)));
+    let mut merged: Vec<StringRegion> = Vec::with_capacity(regions.len());
+    for region in regions.drain(..) {
+        match merged.last_mut() {
+            Some(last)
+                if last.path == region.path && region.start <= last.end.saturating_add(max_gap) =>
+            {
+                last.end = last.end.max(region.end);
+            }
+            _ => merged.push(StringRegion::new_inner(
+                region.path,
+                region.start,
+                region.end,
+            )),
+        }
+    }
+    *regions = merged;
+}
+
+/// Same as `merge_overlaps`, leaving the input untouched.
+pub fn merged(regions: &[StringRegion], max_gap: u64) -> Vec<StringRegion> {
+    let mut regions = regions.to_vec();
+    merge_overlaps(&mut regions, max_gap);
+    regions
+}
+
+/// Sorts `regions` by `(path, start, end)` and removes duplicates under `PartialEq`, i.e. the
+/// same coordinates, orientation and strand; names and scores are not compared. The first of
+/// each group of duplicates is kept. Returns the number of regions removed.
+pub fn dedup_regions(regions: &mut Vec<StringRegion>) -> usize {
+    regions.sort_by(|a, b| (&a.path, a.start, a.end).cmp(&(&b.path, b.start, b.end)));
+    dedup_sorted(regions, |a, b| a == b)
+}
+
+/// Same as `dedup_regions`, but regions with the same `canonical` key are duplicates: forward
+/// and inverted twins, different strands and `chr1`/`1` spellings collapse into the region
+/// listed first. Regions are sorted by the canonical key.
+pub fn dedup_by_coordinates(regions: &mut Vec<StringRegion>) -> usize {
+    regions.sort_by(|a, b| a.canonical_key().cmp(&b.canonical_key()));
+    dedup_sorted(regions, |a, b| a.canonical_key() == b.canonical_key())
+}
+
+/// Drops every region equal under `same` to a region kept before it, provided equal regions
+/// have equal coordinates, which are sorted.
+fn dedup_sorted<F>(regions: &mut Vec<StringRegion>, same: F) -> usize
+where
+    F: Fn(&StringRegion, &StringRegion) -> bool,
+{
+    let before = regions.len();
+    let mut kept: Vec<StringRegion> = Vec::with_capacity(before);
+    let mut run = 0;
+    for region in regions.drain(..) {
+        if !kept.last().is_some_and(|last| {
+            eq_ignore_chr_prefix(&last.path, &region.path)
+                && (last.start, last.end) == (region.start, region.end)
+        }) {
+            run = kept.len();
+        }
+        if !kept[run..].iter().any(|k| same(k, &region)) {
+            kept.push(region);
+        }
+    }
+    *regions = kept;
+    before - regions.len()
+}
+
+/// How `normalize_set` rewrites the `chr` prefix of contig names.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ChrPrefix {
+    #[default]
+    Keep,
+    /// `chr1` becomes `1`.
+    Strip,
+    /// `1` becomes `chr1`.
+    Add,
+}
+
+/// Steps applied by `normalize_set` besides sorting and removing exact duplicates.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct NormalizeOptions {
+    /// Contig names to replace, e.g. `MT` to `chrM`. Applied before `prefix`.
+    pub aliases: HashMap<String, String>,
+    pub prefix: ChrPrefix,
+    /// Deduplicate with `dedup_by_coordinates` instead of `dedup_regions`.
+    pub by_coordinates: bool,
+    /// Merge regions whose gaps are at most this many bases with `merge_overlaps`.
+    pub merge_gap: Option<u64>,
+}
+
+/// What `normalize_set` removed, for logging.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct NormalizeStats {
+    /// Regions dropped as duplicates.
+    pub duplicates: usize,
+    /// Regions absorbed by merging, i.e. the drop in count caused by `merge_gap`.
+    pub merged: usize,
+}
+
+/// Renames contigs (aliases, then the prefix policy), sorts, deduplicates and optionally merges
+/// `regions` in one call. Merging keeps only the coordinates, as `merge_overlaps` does.
+pub fn normalize_set(
+    mut regions: Vec<StringRegion>,
+    opts: &NormalizeOptions,
+) -> (Vec<StringRegion>, NormalizeStats) {
+    for region in regions.iter_mut() {
+        if let Some(alias) = opts.aliases.get(&region.path) {
+            region.path = alias.clone();
+        }
+        match opts.prefix {
+            ChrPrefix::Keep => {}
+            ChrPrefix::Strip => {
+                if let Some(path) = region.path.strip_prefix("chr") {
+                    region.path = path.to_string();
+                }
+            }
+            ChrPrefix::Add => {
+                if !region.path.starts_with("chr") {
+                    region.path = format!("chr{}", region.path);
+                }
+            }
+        }
+    }
+    let duplicates = if opts.by_coordinates {
+        dedup_by_coordinates(&mut regions)
+    } else {
+        dedup_regions(&mut regions)
+    };
+    let before = regions.len();
+    if let Some(max_gap) = opts.merge_gap {
+        merge_overlaps(&mut regions, max_gap);
+    }
+    let merged = before - regions.len();
+    (regions, NormalizeStats { duplicates, merged })
+}
+
+/// Removes from every region of `a` the parts overlapping any region of `b`, like
+/// `bedtools subtract`. `b` is merged and sorted once, then each region of `a` is punched by
+/// binary search, so the cost is O((n + m) log m) plus the number of fragments. Fragments
+/// come in the order of `a`, ascending within each region, and keep the orientation, strand
+/// and annotations of their source (see `StringRegion::subtract`).
+pub fn subtract_sets(a: &[StringRegion], b: &[StringRegion]) -> Vec<StringRegion> {
+    subtract_sets_min_len(a, b, 0)
+}
+
+/// Same as `subtract_sets`, dropping fragments shorter than `min_len` bases.
+pub fn subtract_sets_min_len(
+    a: &[StringRegion],
+    b: &[StringRegion],
+    min_len: u64,
+) -> Vec<StringRegion> {
+    let b = merged(b, 0);
+    let mut fragments = vec![];
+    for region in a {
+        let on_path = {
+            let first = b.partition_point(|m| m.path.as_str() < region.path.as_str());
+            let last = b.partition_point(|m| m.path.as_str() <= region.path.as_str());
+            &b[first..last]
+        };
+        let first = on_path.partition_point(|m| m.end <= region.start);
+        let mut rest = Some(region.clone());
+        for mask in on_path[first..].iter().take_while(|m| m.start < region.end) {
+            let current = match rest.take() {
+                Some(current) => current,
+                None => break,
+            };
+            for piece in current.subtract(mask) {
+                if piece.end <= mask.start {
+                    fragments.push(piece);
+                } else {
+                    rest = Some(piece);
+                }
+            }
+        }
+        fragments.extend(rest);
+    }
+    fragments.retain(|fragment| fragment.interval() >= min_len);
+    fragments
+}
+
+/// Sorts regions by score, highest first. NaN scores follow the numeric ones and regions
+/// without a score go last; the sort is stable.
+pub fn sort_by_score_desc<R: AsRef<StringRegion>>(regions: &mut [R]) {
+    let rank = |score: Option<f64>| match score {
+        Some(score) if !score.is_nan() => 0,
+        Some(_) => 1,
+        None => 2,
+    };
+    regions.sort_by(|a, b| {
+        let (a, b) = (a.as_ref().score, b.as_ref().score);
+        rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+            (Some(a), Some(b)) if rank(Some(a)) == 0 => b.total_cmp(&a),
+            _ => Ordering::Equal,
+        })
+    });
+}
+
+/// Keeps the regions whose score is at least `min`. Regions without a score are dropped.
+pub fn filter_by_score<R: AsRef<StringRegion>>(regions: Vec<R>, min: f64) -> Vec<R> {
+    regions
+        .into_iter()
+        .filter(|region| region.as_ref().score.is_some_and(|score| score >= min))
+        .collect()
+}
+
+/// Convention a pair of coordinates is written in. Regions in this crate are stored
+/// `ZeroHalfOpen` (BED style); `OneClosed` is the samtools/GFF style where `chr1:1-10` is the
+/// first ten bases. `StringRegion::new` stores the numbers as written, so use `parse_as` or
+/// the conversion methods when the input is known to be 1-based. `StringRegion::system`
+/// reports the system a constructor recorded, if any.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CoordinateSystem {
+    ZeroHalfOpen,
+    OneClosed,
+}
+
+/// Options for `StringRegion::parse_with`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Convention the input is written in, recorded on the parsed region as
+    /// `StringRegion::system`. The coordinates themselves are not converted.
+    pub system: Option<CoordinateSystem>,
+}
+
+/// How to read `end` in the `*_with` predicates.
+///
+/// Every parser and constructor in this crate (`StringRegion::new`, `from_bed_fields`,
+/// `from_gff_fields`, `Region::parse`, ...) produces `HalfOpen` regions; `Closed` is for
+/// coordinates that were stored as given by a closed-interval source such as GFF columns or
+/// `samtools` region strings without conversion.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Closedness {
+    /// `end` is one past the last base, as in BED.
+    HalfOpen,
+    /// `end` is the last included base.
+    Closed,
+}
+
+impl Closedness {
+    /// The exclusive end of `[start, end]` or `[start, end)` under this convention.
+    fn exclusive_end(self, end: u64) -> u64 {
+        match self {
+            Closedness::HalfOpen => end,
+            Closedness::Closed => end.saturating_add(1),
+        }
+    }
+}
+
+/// Direction in which `align_to` snaps region boundaries to the grid.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AlignMode {
+    /// Round `start` down and `end` up, enclosing the original region.
+    Expand,
+    /// Round `start` up and `end` down, staying inside the original region.
+    Shrink,
+}
+
+/// Position of a region relative to a stranded feature, as returned by
+/// `StringRegion::relative_position`. The distances are the `gap` in bases.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativePosition {
+    Upstream(u64),
+    Downstream(u64),
+    Overlapping,
+}
+
+/// How two regions relate, as returned by `Region::classify`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OverlapKind {
+    /// Same reference, separated by at least one base.
+    Disjoint,
+    /// Same reference, one ends exactly where the other starts.
+    Abutting,
+    /// Partial overlap, neither contains the other.
+    Overlapping,
+    /// `self` encloses `other`.
+    Contains,
+    /// `other` encloses `self`.
+    ContainedBy,
+    /// Identical coordinates.
+    Equal,
+    DifferentRef,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Region {
+    ref_id: u64,
+    start: u64,
+    end: u64,
+}
+
+impl Region {
+    /// Creates new region. `ref_id` is 0-based, `start-end` is 0-based half-open interval.
+    pub fn new(ref_id: u64, start: u64, end: u64) -> Region {
+        assert!(
+            start <= end,
+            "Region: start should not be greater than end ({} > {})",
+            start,
+            end
+        );
+        Region { ref_id, start, end }
+    }
+
+    /// Attaches `strand`, for strand-aware flanking with `StrandedRegion::upstream` and
+    /// `StrandedRegion::downstream`.
+    pub fn into_stranded(self, strand: Strand) -> StrandedRegion {
+        StrandedRegion {
+            region: self,
+            strand,
+        }
+    }
+
+    /// `[center - half_width, center + half_width)`, with the start clipped at 0.
+    pub fn from_center(ref_id: u64, center: u64, half_width: u64) -> Region {
+        Region {
+            ref_id,
+            start: center.saturating_sub(half_width),
+            end: center.saturating_add(half_width),
+        }
+    }
+
+    /// Converts a `StringRegion`, resolving its path with `to_id`. The orientation is
+    /// dropped; see `convert_oriented` to keep it. A region with `start > end` is rejected
+    /// rather than violating the invariant of `new`.
+    pub fn convert<F>(
+        path: &StringRegion,
+        to_id: F,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        if path.start > path.end {
+            return Err(format!(
+                "Error: inconsistent region, start {} is after end {}.",
+                path.start, path.end
+            )
+            .into());
+        }
+        Ok(Region {
+            ref_id: to_id(&path.path).ok_or("Error: the reference id is not recognized.")?,
+            start: path.start,
+            end: path.end,
+        })
+    }
+
+    /// Same as `convert`, also reporting whether `path` was inverted.
+    pub fn convert_oriented<F>(
+        path: &StringRegion,
+        to_id: F,
+    ) -> std::result::Result<OrientedRegion, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        Ok(OrientedRegion {
+            region: Region::convert(path, to_id)?,
+            reverse: path.inverted,
+        })
+    }
+
+    /// Parses `chr:start-end`, resolving the path with `to_id`. A reversed range such as
+    /// `chr1:200-100` is normalized to `start <= end`; see `parse_oriented` to keep the
+    /// orientation.
+    pub fn parse<F>(path: &str, to_id: F) -> std::result::Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        Ok(Region::parse_oriented(path, to_id)?.region)
+    }
+
+    /// Same as `parse`, but a bare contig name without `:` spans the whole contig,
+    /// `[0, contig_len(name))`. Fails if `contig_len` does not know the name.
+    pub fn parse_or_whole<F, G>(
+        path: &str,
+        to_id: F,
+        contig_len: G,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<u64>,
+        G: Fn(&str) -> Option<u64>,
+    {
+        if path.contains(':') {
+            return Region::parse(path, to_id);
+        }
+        Ok(Region {
+            ref_id: to_id(path).ok_or("Error: the reference id is not recognized.")?,
+            start: 0,
+            end: contig_len(path).ok_or("Error: the reference length is not known.")?,
+        })
+    }
+
+    /// Same as `parse`, also reporting whether the range was written with `start > end`.
+    pub fn parse_oriented<F>(
+        path: &str,
+        to_id: F,
+    ) -> std::result::Result<OrientedRegion, Box<dyn std::error::Error>>
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = coordinate_regex(&RE, r"^(.+):((?:{0})?)-?((?:{0})?)$");
+        let caps = re.captures(path).ok_or("Invalid genomic range")?;
+        let path = caps.get(1).map(|t| t.as_str()).ok_or("Parse Path Error")?;
+        let start = optional_coordinate(caps.get(2))?
+            .ok_or("Error: the reference start is not recognized.")?;
+        let end = optional_coordinate(caps.get(3))?
+            .ok_or("Error: the reference end is not recognized.")?;
+
+        Ok(OrientedRegion {
+            region: Region {
+                ref_id: to_id(path).ok_or("Error: the reference id is not recognized.")?,
+                start: start.min(end),
+                end: start.max(end),
+            },
+            reverse: start > end,
+        })
+    }
+
+    pub fn ref_id(&self) -> u64 {
+        self.ref_id
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
     }
 
     pub fn end(&self) -> u64 {
         self.end
     }
 
-    pub fn len(&self) -> u64 {
-        self.end - self.start
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The bounds to print, e.g. on a ruler: `(start, end)` as stored for 0-based half-open
+    /// output, `(start + 1, end)` for 1-based closed output, where the last base keeps its
+    /// number. An empty region prints with `end` one below `start` in the 1-based form.
+    pub fn display_bounds(&self, one_based: bool) -> (u64, u64) {
+        if one_based {
+            (self.start.saturating_add(1), self.end)
+        } else {
+            (self.start, self.end)
+        }
+    }
+
+    /// Defensive `len()` returning `None` instead of underflowing if `start > end`.
+    pub fn checked_len(&self) -> Option<u64> {
+        self.end.checked_sub(self.start)
+    }
+
+    /// Center position, `start + len() / 2`: the middle base for odd lengths and the upper of
+    /// the two central bases for even lengths.
+    pub fn midpoint(&self) -> u64 {
+        self.start + self.len() / 2
+    }
+
+    /// A region of `new_len` bases around the same midpoint, clipped at position 0.
+    pub fn resize_centered(&self, new_len: u64) -> Region {
+        let (start, end) = centered_bounds(self.midpoint(), new_len);
+        Region::new(self.ref_id, start, end)
+    }
+
+    /// A region grown by `left` bases before `start` (clipped at 0) and `right` bases after
+    /// `end`, like `bedtools slop -l -r`.
+    pub fn pad(&self, left: u64, right: u64) -> Region {
+        Region {
+            ref_id: self.ref_id,
+            start: self.start.saturating_sub(left),
+            end: self.end.saturating_add(right),
+        }
+    }
+
+    /// The inverse of a symmetric `pad`: drops `len` bases from each end. A region too short
+    /// to lose `2 * len` bases collapses to an empty region at its `midpoint`.
+    pub fn shrink(&self, len: u64) -> Region {
+        if len.saturating_mul(2) >= self.len() {
+            let mid = self.midpoint();
+            return Region::new(self.ref_id, mid, mid);
+        }
+        Region::new(self.ref_id, self.start + len, self.end - len)
+    }
+
+    /// Same as `pad`, also clipping `end` at `max_end`, typically the contig length.
+    pub fn pad_within(&self, left: u64, right: u64, max_end: u64) -> Region {
+        let mut region = self.pad(left, right);
+        region.end = region.end.min(max_end);
+        region.start = region.start.min(region.end);
+        region
+    }
+
+    /// A `width`-bp window centered on `midpoint()`, e.g. a motif window around a peak
+    /// summit. Same as `resize_centered`.
+    pub fn midpoint_region(&self, width: u64) -> Region {
+        self.resize_centered(width)
+    }
+
+    /// Grows the region symmetrically until it is at least `min` bases long. An odd extra base
+    /// goes to the end, and whatever cannot be added before position 0 is added to the end
+    /// instead, so the result is always `min` long.
+    pub fn expand_to_min_len(&mut self, min: u64) {
+        if self.len() >= min {
+            return;
+        }
+        let extra = min - self.len();
+        let start = self.start.saturating_sub(extra / 2);
+        self.end = self.end.saturating_add(extra - (self.start - start));
+        self.start = start;
+    }
+
+    /// Splits into `n` contiguous bins whose lengths differ by at most one, the leading bins
+    /// taking the remainder. When `n` exceeds the length, only `len()` single-base bins are
+    /// returned; `n = 0` or an empty region yields no bins.
+    pub fn split_evenly(&self, n: usize) -> Vec<Region> {
+        let mut cursor = self.start;
+        even_sizes(self.len(), n)
+            .into_iter()
+            .map(|size| {
+                cursor += size;
+                Region::new(self.ref_id, cursor - size, cursor)
+            })
+            .collect()
+    }
+
+    /// Lazily yields the full windows of `size` bases starting every `step` bases from `start`.
+    /// A trailing window that would extend past `end` is dropped; see `windows_partial`.
+    /// `size` or `step` of 0 is an error.
+    pub fn windows(&self, size: u64, step: u64) -> Result<Windows, RegionParseError> {
+        Ok(Windows {
+            ref_id: self.ref_id,
+            bounds: WindowBounds::new(self.start, self.end, size, step, false)?,
+        })
+    }
+
+    /// Same as `windows`, but every window starting inside the region is emitted, clipped at
+    /// `end`, as `bedtools makewindows` does.
+    pub fn windows_partial(&self, size: u64, step: u64) -> Result<Windows, RegionParseError> {
+        Ok(Windows {
+            ref_id: self.ref_id,
+            bounds: WindowBounds::new(self.start, self.end, size, step, true)?,
+        })
+    }
+
+    /// Indices `k` of the genome-wide bins `[k * bin, (k + 1) * bin)` the region touches, i.e.
+    /// `start / bin ..= (end - 1) / bin`. An empty region, or a `bin` of 0, touches none.
+    pub fn bucket(&self, bin: u64) -> impl Iterator<Item = u64> {
+        if bin == 0 || self.start == self.end {
+            return 0..0;
+        }
+        self.start / bin..self.end.div_ceil(bin)
+    }
+
+    /// Splits at every multiple of `bin` inside the region, so the first and last pieces may be
+    /// shorter than `bin`. Unlike `windows(bin, bin)`, the grid starts at position 0 rather
+    /// than at `start`. `bin` of 0 is an error.
+    pub fn split_on_bins(&self, bin: u64) -> Result<Vec<Region>, RegionParseError> {
+        if bin == 0 {
+            return Err(RegionParseError::InvalidPosition(
+                "bin size must be positive".to_string(),
+            ));
+        }
+        let mut pieces = vec![];
+        let mut cursor = self.start;
+        while cursor < self.end {
+            let next = (cursor / bin)
+                .saturating_add(1)
+                .saturating_mul(bin)
+                .min(self.end);
+            pieces.push(Region::new(self.ref_id, cursor, next));
+            cursor = next;
+        }
+        Ok(pieces)
+    }
+
+    /// Snaps both ends to multiples of `bin` as directed by `mode`. `Shrink` yields `None`
+    /// when no whole bin fits inside the region. `bin` of 0 is an error.
+    pub fn align_to(&self, bin: u64, mode: AlignMode) -> Result<Option<Region>, RegionParseError> {
+        Ok(
+            align_bounds(self.start, self.end, bin, mode)?.map(|(start, end)| Region {
+                ref_id: self.ref_id,
+                start,
+                end,
+            }),
+        )
+    }
+
+    /// Splits at `pos` into `[start, pos)` and `[pos, end)`. `None` unless
+    /// `start < pos < end`.
+    pub fn split_at(&self, pos: u64) -> Option<(Region, Region)> {
+        if pos <= self.start || self.end <= pos {
+            return None;
+        }
+        Some((
+            Region::new(self.ref_id, self.start, pos),
+            Region::new(self.ref_id, pos, self.end),
+        ))
+    }
+
+    /// Splits at every breakpoint strictly inside the region, returning fragments that tile
+    /// it exactly in ascending order. Breakpoints may be unsorted and repeated; those outside
+    /// or on the boundaries are ignored, so no fragment is empty.
+    pub fn split_at_many(&self, positions: &[u64]) -> Vec<Region> {
+        fragment_bounds(self.start, self.end, positions)
+            .into_iter()
+            .map(|(start, end)| Region::new(self.ref_id, start, end))
+            .collect()
+    }
+
+    /// Same as `split_at_many`, failing with `InvalidPosition` for a breakpoint outside
+    /// `[start, end]`.
+    pub fn split_at_many_strict(&self, positions: &[u64]) -> Result<Vec<Region>, RegionParseError> {
+        check_breakpoints(self.start, self.end, positions)?;
+        Ok(self.split_at_many(positions))
+    }
+
+    pub fn set_ref_id(&mut self, ref_id: u64) {
+        self.ref_id = ref_id;
+    }
+
+    /// A copy on `ref_id`, e.g. for `map`ping regions to another reference dictionary.
+    pub fn with_ref_id(&self, ref_id: u64) -> Region {
+        Region { ref_id, ..*self }
+    }
+
+    pub fn set_start(&mut self, start: u64) {
+        assert!(
+            start <= self.end,
+            "Region: start should not be greater than end ({} > {})",
+            start,
+            self.end
+        );
+        self.start = start;
+    }
+
+    pub fn set_end(&mut self, end: u64) {
+        assert!(
+            self.start <= end,
+            "Region: start should not be greater than end ({} > {})",
+            self.start,
+            end
+        );
+        self.end = end;
+    }
+
+    /// Whether `pos` lies in `[start, end)` on `ref_id`.
+    ///
+    /// The containment family, all on the same reference and half-open:
+    ///
+    /// | method                 | holds when                                    |
+    /// |------------------------|-----------------------------------------------|
+    /// | `a.contains(r, p)`     | `a.start <= p < a.end`                        |
+    /// | `a.contains_region(b)` | `a.start <= b.start && b.end <= a.end`        |
+    /// | `a.is_contained_in(b)` | `b.contains_region(a)`                        |
+    /// | `a.overlaps(b)`        | `max(a.start, b.start) < min(a.end, b.end)`   |
+    ///
+    /// So every region contains itself, and a non-empty `b` with `a.contains_region(b)` also
+    /// satisfies `a.overlaps(b)` and `a.contains(r, p)` for every position `p` of `b`.
+    pub fn contains(&self, ref_id: u64, pos: u64) -> bool {
+        self.ref_id == ref_id && self.start <= pos && pos < self.end
+    }
+
+    /// Same as `contains`, but a position on another reference is an error rather than
+    /// `false`, so a query against the wrong chromosome does not pass for a miss.
+    pub fn contains_checked(&self, ref_id: u64, pos: u64) -> Result<bool, RegionError> {
+        if self.ref_id != ref_id {
+            return Err(RegionError::DifferentReference);
+        }
+        Ok(self.contains(ref_id, pos))
+    }
+
+    /// Same as `contains`, reading `end` under `closedness`.
+    pub fn contains_with(&self, ref_id: u64, pos: u64, closedness: Closedness) -> bool {
+        self.ref_id == ref_id && self.start <= pos && pos < closedness.exclusive_end(self.end)
+    }
+
+    /// Whether `other` lies entirely within `self`; shared ends are allowed, so a region
+    /// contains itself. See `contains` for the whole family.
+    pub fn contains_region(&self, other: &Region) -> bool {
+        self.ref_id == other.ref_id && self.start <= other.start && other.end <= self.end
+    }
+
+    /// Whether `self` lies entirely within `other`, the mirror of `contains_region`.
+    pub fn is_contained_in(&self, other: &Region) -> bool {
+        other.contains_region(self)
+    }
+
+    /// Same as `contains`, for call sites reading better as an overlap test.
+    pub fn overlaps_point(&self, ref_id: u64, pos: u64) -> bool {
+        self.contains(ref_id, pos)
+    }
+
+    /// Whether `[start, end)` on `ref_id` lies entirely within `self`, as `contains_region`
+    /// for raw coordinates. `false` when `start > end`.
+    pub fn covers_range(&self, ref_id: u64, start: u64, end: u64) -> bool {
+        start <= end && self.include(&Region { ref_id, start, end })
+    }
+
+    /// Same as `contains_region`, kept under its original name.
+    pub fn include(&self, range: &Region) -> bool {
+        self.contains_region(range)
+    }
+
+    pub fn same_reference(&self, other: &Region) -> bool {
+        self.ref_id == other.ref_id
+    }
+
+    /// Whether the two half-open intervals share at least one base on the same reference.
+    /// Regions that only touch (`self.end == other.start`) do not overlap.
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.ref_id == other.ref_id && self.overlaps_range(other.start, other.end)
+    }
+
+    /// Same as `overlaps`, reading the `end` of both regions under `closedness`.
+    pub fn overlaps_with(&self, other: &Region, closedness: Closedness) -> bool {
+        self.ref_id == other.ref_id
+            && self.start.max(other.start)
+                < closedness
+                    .exclusive_end(self.end)
+                    .min(closedness.exclusive_end(other.end))
+    }
+
+    /// Whether the two regions share a boundary on the same reference (`self.end == other.start`
+    /// or the reverse). Adjacent non-empty regions never `overlaps`.
+    pub fn is_adjacent(&self, other: &Region) -> bool {
+        self.ref_id == other.ref_id && (self.end == other.start || other.end == self.start)
+    }
+
+    /// Same as `is_adjacent`: the regions abut exactly, e.g. consecutive tiles.
+    pub fn adjacent(&self, other: &Region) -> bool {
+        self.is_adjacent(other)
+    }
+
+    /// Whether the two regions overlap or are adjacent on the same reference. This is exactly
+    /// the condition under which `union` returns `Some`; for non-empty regions it equals
+    /// `overlaps || is_adjacent`.
+    pub fn touches(&self, other: &Region) -> bool {
+        self.ref_id == other.ref_id && self.start.max(other.start) <= self.end.min(other.end)
+    }
+
+    /// Same as `overlaps` against `[start, end)`, without checking the reference.
+    pub fn overlaps_range(&self, start: u64, end: u64) -> bool {
+        self.start.max(start) < self.end.min(end)
+    }
+
+    /// The overlapping part of two regions on the same reference, or `None` if they do not
+    /// share a base. Touching regions do not intersect.
+    pub fn intersect(&self, other: &Region) -> Option<Region> {
+        self.intersect_allow_empty(other)
+            .filter(|region| !region.is_empty())
+    }
+
+    /// Number of bases shared with `other`; 0 on different references.
+    pub fn overlap_len(&self, other: &Region) -> u64 {
+        self.intersect(other).map_or(0, |region| region.len())
+    }
+
+    /// Intersection length over union length, between 0 and 1. Two empty regions score 0.
+    pub fn jaccard(&self, other: &Region) -> f64 {
+        let intersection = self.overlap_len(other);
+        let union = self.len() + other.len() - intersection;
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as f64 / union as f64
+    }
+
+    /// Clips `self` to a viewport-like `container`; `intersect` under a clearer name.
+    pub fn fit_within(&self, container: &Region) -> Option<Region> {
+        self.intersect(container)
+    }
+
+    /// Clips a feature to `bounds`; `intersect` under a name stating the asymmetric intent.
+    pub fn trim_to(&self, bounds: &Region) -> Option<Region> {
+        self.intersect(bounds)
+    }
+
+    /// Trims every feature to `bounds` in place, dropping those left empty.
+    pub fn trim_all(features: &mut Vec<Region>, bounds: &Region) {
+        *features = features.iter().filter_map(|f| f.trim_to(bounds)).collect();
+    }
+
+    /// Same as `intersect`, but regions that only touch yield a zero-length region.
+    pub fn intersect_allow_empty(&self, other: &Region) -> Option<Region> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if self.ref_id != other.ref_id || start > end {
+            return None;
+        }
+        Some(Region {
+            ref_id: self.ref_id,
+            start,
+            end,
+        })
+    }
+
+    /// Merges two regions on the same reference that overlap or are adjacent. Unlike
+    /// `overlaps`, adjacency (`self.end == other.start` or the reverse) is enough to merge.
+    pub fn union(&self, other: &Region) -> Option<Region> {
+        self.merge_within(other, 0)
+    }
+
+    /// Same as `union`, reporting why the regions could not be merged.
+    pub fn try_union(&self, other: &Region) -> Result<Region, RegionError> {
+        if self.ref_id != other.ref_id {
+            return Err(RegionError::DifferentReference);
+        }
+        self.union(other).ok_or(RegionError::Disjoint)
+    }
+
+    /// Merges two regions on the same reference when the gap between them is at most
+    /// `max_gap` bases, like `bedtools merge -d`. `max_gap = 0` behaves as `union`.
+    pub fn merge_within(&self, other: &Region, max_gap: u64) -> Option<Region> {
+        let gap = self
+            .start
+            .max(other.start)
+            .saturating_sub(self.end.min(other.end));
+        if gap > max_gap {
+            return None;
+        }
+        self.hull(other)
+    }
+
+    /// The smallest region enclosing both, even across a gap, if the references match.
+    pub fn hull(&self, other: &Region) -> Option<Region> {
+        if self.ref_id != other.ref_id {
+            return None;
+        }
+        Some(Region {
+            ref_id: self.ref_id,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
+
+    /// Removes `other` from `self`, leaving zero, one or two fragments in ascending order.
+    /// Fragments are never empty, so an empty `self` leaves none.
+    pub fn subtract(&self, other: &Region) -> Vec<Region> {
+        self.mask(std::slice::from_ref(other))
+    }
+
+    /// Returns the pieces of `self` not covered by any mask on the same reference, in order.
+    /// Masks may overlap each other and need not be sorted.
+    pub fn mask(&self, masks: &[Region]) -> Vec<Region> {
+        let mut hits: Vec<&Region> = masks.iter().filter(|m| self.overlaps(m)).collect();
+        hits.sort_by(|a, b| a.cmp_by_coordinate(b));
+        let mut pieces = vec![];
+        let mut cursor = self.start;
+        for m in hits {
+            if m.start > cursor {
+                pieces.push(Region::new(self.ref_id, cursor, m.start));
+            }
+            cursor = cursor.max(m.end);
+        }
+        if cursor < self.end {
+            pieces.push(Region::new(self.ref_id, cursor, self.end));
+        }
+        pieces
+    }
+
+    /// The sub-regions of `self` left uncovered by `intervals`, e.g. the uncaptured parts of a
+    /// target; the depth-0 runs of `coverage`. Same as `mask`.
+    pub fn gaps(&self, intervals: &[Region]) -> Vec<Region> {
+        self.mask(intervals)
+    }
+
+    /// Moves both ends by `delta`, returning `None` instead of clamping when `start` would
+    /// go below 0 or `end` would overflow.
+    pub fn shift_checked(&self, delta: i64) -> Option<Region> {
+        Some(Region {
+            ref_id: self.ref_id,
+            start: shift_checked(self.start, delta)?,
+            end: shift_checked(self.end, delta)?,
+        })
+    }
+
+    /// Same as `shift_checked`, failing with `InvalidPosition` for use with `?`.
+    pub fn try_shift(&self, delta: i64) -> Result<Region, RegionParseError> {
+        self.shift_checked(delta)
+            .ok_or_else(|| shift_error(self.start, self.end, delta))
+    }
+
+    /// Moves both ends by `delta`, saturating each at 0 and `u64::MAX`: a region pushed past
+    /// the origin shrinks against it instead of wrapping.
+    pub fn shift(&self, delta: i64) -> Region {
+        Region {
+            ref_id: self.ref_id,
+            start: shift_saturating(self.start, delta),
+            end: shift_saturating(self.end, delta),
+        }
+    }
+
+    /// The same bases counted on the reverse complement of a `len`-bp reference:
+    /// `[len - end, len - start)`. Applying it twice gives back the original region. Fails with
+    /// `InvalidPosition` if `end > len`.
+    pub fn reverse_coordinates(&self, len: u64) -> Result<Region, RegionParseError> {
+        let (start, end) = mirror_bounds(self.start, self.end, len)?;
+        Ok(Region::new(self.ref_id, start, end))
+    }
+
+    /// Number of bases between the two regions on the same reference; 0 when they overlap or
+    /// touch.
+    pub fn gap(&self, other: &Region) -> Option<u64> {
+        if self.ref_id != other.ref_id {
+            return None;
+        }
+        Some(
+            self.start
+                .max(other.start)
+                .saturating_sub(self.end.min(other.end)),
+        )
+    }
+
+    /// Signed `gap`: positive when `other` lies downstream of `self`, negative when upstream.
+    /// Overlapping and touching regions (`self.end == other.start`) are both at distance 0.
+    /// `None` for different references or a gap that does not fit in an `i64`.
+    pub fn distance(&self, other: &Region) -> Option<i64> {
+        signed_gap(self.gap(other)?, other.start >= self.end)
+    }
+
+    /// Classifies the relationship of `other` to `self`. Containment allows shared ends, so a
+    /// region sharing only its start with a longer one is `ContainedBy`.
+    pub fn classify(&self, other: &Region) -> OverlapKind {
+        if self.ref_id != other.ref_id {
+            OverlapKind::DifferentRef
+        } else if self.start == other.start && self.end == other.end {
+            OverlapKind::Equal
+        } else if self.contains_closed(other) {
+            OverlapKind::Contains
+        } else if other.contains_closed(self) {
+            OverlapKind::ContainedBy
+        } else if self.overlaps(other) {
+            OverlapKind::Overlapping
+        } else if self.end == other.start || other.end == self.start {
+            OverlapKind::Abutting
+        } else {
+            OverlapKind::Disjoint
+        }
+    }
+
+    /// Same as `contains_region`: both ends of `other` may coincide with those of `self`.
+    pub fn contains_closed(&self, other: &Region) -> bool {
+        self.contains_region(other)
+    }
+
+    /// The regions of `others` lying entirely within `self`, shared ends allowed (see
+    /// `contains_closed`).
+    pub fn iter_contained<'a>(&'a self, others: &'a [Region]) -> impl Iterator<Item = &'a Region> {
+        others
+            .iter()
+            .filter(move |other| self.contains_closed(other))
+    }
+
+    /// Depth of `intervals` over `self` as `(start, end, depth)` runs that tile the region in
+    /// order, including runs of depth 0. Only intervals on the same reference count, and each
+    /// is clipped to `self`. Consecutive runs always differ in depth.
+    pub fn coverage(&self, intervals: &[Region]) -> Vec<(u64, u64, u32)> {
+        let mut events: Vec<(u64, i64)> = vec![];
+        for interval in intervals {
+            if let Some(clipped) = self.intersect(interval) {
+                events.push((clipped.start, 1));
+                events.push((clipped.end, -1));
+            }
+        }
+        events.sort_unstable();
+        let mut runs: Vec<(u64, u64, u32)> = vec![];
+        let mut push_run = |start: u64, end: u64, depth: i64| {
+            if start >= end {
+                return;
+            }
+            match runs.last_mut() {
+                Some(last) if last.2 as i64 == depth => last.1 = end,
+                _ => runs.push((start, end, depth as u32)),
+            }
+        };
+        let (mut pos, mut depth) = (self.start, 0i64);
+        for (at, delta) in events {
+            push_run(pos, at, depth);
+            pos = at;
+            depth += delta;
+        }
+        push_run(pos, self.end, depth);
+        runs
+    }
+
+    /// Packs `(ref_id, start, end)` into one integer ordered like the tuple, for
+    /// `sort_by_key` over large inputs. `ref_id` gets the top 32 bits and `start`/`end` 48 bits
+    /// each, so the key is only meaningful when `is_packable` holds; use `sort_regions` to fall
+    /// back to the tuple comparison automatically.
+    pub fn sort_key(&self) -> u128 {
+        debug_assert!(self.is_packable(), "region does not fit in a sort key");
+        ((self.ref_id as u128) << 96) | ((self.start as u128) << 48) | self.end as u128
+    }
+
+    /// Whether `ref_id` fits in 32 bits and `start`/`end` in 48 bits, as `sort_key` requires.
+    pub fn is_packable(&self) -> bool {
+        self.ref_id >> 32 == 0 && self.start >> 48 == 0 && self.end >> 48 == 0
+    }
+
+    /// Compares `(start, end)` only, ignoring `ref_id`. Meaningful only for regions on the
+    /// same chromosome, e.g. `regions.sort_by(Region::cmp_by_coordinate)` after grouping.
+    pub fn cmp_by_coordinate(&self, other: &Region) -> Ordering {
+        (self.start, self.end).cmp(&(other.start, other.end))
+    }
+}
+
+/// Parses the two ends of a BEDPE record (`chrom1 start1 end1 chrom2 start2 end2 ...`),
+/// both 0-based half-open, resolving the contig names with `to_id`. Positions must be plain
+/// integers (see `parse_integer_coordinate`). Columns past the sixth are ignored.
+pub fn parse_bedpe_line<F>(line: &str, to_id: F) -> Result<(Region, Region), RegionParseError>
+where
+    F: Fn(&str) -> Option<u64>,
+{
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 6 {
+        return Err(RegionParseError::InvalidFormat(format!(
+            "BEDPE record requires at least 6 fields: {}",
+            line
+        )));
+    }
+    let region = |fields: &[&str]| -> Result<Region, RegionParseError> {
+        let ref_id = to_id(fields[0])
+            .ok_or_else(|| RegionParseError::UnknownContig(fields[0].to_string()))?;
+        let (start, end) = (
+            parse_integer_coordinate(fields[1])?,
+            parse_integer_coordinate(fields[2])?,
+        );
+        if start > end {
+            return Err(RegionParseError::InvalidPosition(format!(
+                "start {} is after end {}",
+                start, end
+            )));
+        }
+        Ok(Region::new(ref_id, start, end))
+    };
+    Ok((region(&fields[0..3])?, region(&fields[3..6])?))
+}
+
+/// Groups regions by `ref_id`, keeping the input order within each group.
+pub fn group_by_reference(regions: Vec<Region>) -> BTreeMap<u64, Vec<Region>> {
+    let mut groups: BTreeMap<u64, Vec<Region>> = BTreeMap::new();
+    for region in regions {
+        groups.entry(region.ref_id).or_default().push(region);
+    }
+    groups
+}
+
+/// Sorts `regions` by `(ref_id, start, end)`, using the packed `sort_key` when every region
+/// fits in it and the tuple comparison otherwise.
+pub fn sort_regions(regions: &mut [Region]) {
+    if regions.iter().all(Region::is_packable) {
+        regions.sort_unstable_by_key(Region::sort_key);
+    } else {
+        regions.sort_unstable_by_key(|r| (r.ref_id, r.start, r.end));
+    }
+}
+
+/// Whether `regions` is already in `sort_regions` order.
+pub fn is_sorted(regions: &[Region]) -> bool {
+    ensure_sorted(regions).is_ok()
+}
+
+/// Checks that `regions` is in `sort_regions` order, failing with `RegionError::Unsorted` and
+/// the index of the first region sorting before its predecessor.
+pub fn ensure_sorted(regions: &[Region]) -> Result<(), RegionError> {
+    first_unsorted(regions, |a, b| {
+        (a.ref_id, a.start, a.end).cmp(&(b.ref_id, b.start, b.end))
+    })
+}
+
+/// Order of contig names used by `sort_string_regions`.
+#[derive(Debug, Clone, Copy)]
+pub enum NameOrder<'a> {
+    /// Byte-wise string order: `chr1 < chr10 < chr2`.
+    Lexical,
+    /// `cmp_natural`: `chr1 < chr2 < chr10`.
+    Natural,
+    /// The order of a reference assembly; see `Karyotype`.
+    Karyotype(&'a Karyotype),
+}
+
+impl<'a> NameOrder<'a> {
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            NameOrder::Lexical => a.cmp(b),
+            NameOrder::Natural => cmp_natural(a, b),
+            NameOrder::Karyotype(karyotype) => karyotype.compare(a, b),
+        }
+    }
+}
+
+/// Contig names in the order of a reference assembly, e.g. as listed in its `.fai` or
+/// `chrom.sizes`. Names it does not list sort after all listed ones, in natural order.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Karyotype {
+    ranks: HashMap<String, usize>,
+}
+
+impl Karyotype {
+    /// Ranks `names` in the given order; a repeated name keeps its first rank.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut ranks = HashMap::new();
+        for name in names {
+            let rank = ranks.len();
+            ranks.entry(name.into()).or_insert(rank);
+        }
+        Karyotype { ranks }
+    }
+
+    /// Position of `name` in the assembly order.
+    pub fn rank(&self, name: &str) -> Option<usize> {
+        self.ranks.get(name).copied()
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match (self.rank(a), self.rank(b)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => cmp_natural(a, b),
+        }
+    }
+}
+
+/// Sorts `regions` by `(path, start, end)` with paths in `order`. The sort is stable, so
+/// regions with equal coordinates keep their input order.
+pub fn sort_string_regions(regions: &mut [StringRegion], order: NameOrder) {
+    regions.sort_by(|a, b| cmp_string_regions(a, b, order));
+}
+
+/// Whether `regions` is already in `sort_string_regions` order.
+pub fn is_sorted_string(regions: &[StringRegion], order: NameOrder) -> bool {
+    ensure_sorted_string(regions, order).is_ok()
+}
+
+/// Same as `ensure_sorted`, for the order of `sort_string_regions`.
+pub fn ensure_sorted_string(regions: &[StringRegion], order: NameOrder) -> Result<(), RegionError> {
+    first_unsorted(regions, |a, b| cmp_string_regions(a, b, order))
+}
+
+fn cmp_string_regions(a: &StringRegion, b: &StringRegion, order: NameOrder) -> Ordering {
+    order
+        .compare(&a.path, &b.path)
+        .then(a.start.cmp(&b.start))
+        .then(a.end.cmp(&b.end))
+}
+
+fn first_unsorted<T, F>(items: &[T], cmp: F) -> Result<(), RegionError>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    match items
+        .windows(2)
+        .position(|pair| cmp(&pair[0], &pair[1]) == Ordering::Greater)
+    {
+        Some(i) => Err(RegionError::Unsorted {
+            input: 0,
+            index: i + 1,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Sorts `regions` by `(ref_id, start, end)` and collapses, in place, every run of regions
+/// whose gaps are at most `max_gap` bases. `max_gap = 0` merges overlapping and adjacent ones.
+pub fn merge_overlaps_within(regions: &mut Vec<Region>, max_gap: u64) {
+    regions.sort_by_key(|r| (r.ref_id, r.start, r.end));
+    let mut merged: Vec<Region> = Vec::with_capacity(regions.len());
+    for region in regions.drain(..) {
+        if let Some(last) = merged.last_mut() {
+            if let Some(hull) = last.merge_within(&region, max_gap) {
+                *last = hull;
+                continue;
+            }
+        }
+        merged.push(region);
+    }
+    *regions = merged;
+}
+
+/// Jaccard index of two sets of regions, like `bedtools jaccard`: each set is merged first,
+/// then the total intersection is divided by the total union. Input order does not matter.
+pub fn jaccard_sets(a: &[Region], b: &[Region]) -> f64 {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    merge_overlaps_within(&mut a, 0);
+    merge_overlaps_within(&mut b, 0);
+    let total = |regions: &[Region]| regions.iter().map(Region::len).sum::<u64>();
+    let (mut i, mut j, mut intersection) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        intersection += a[i].overlap_len(&b[j]);
+        if (a[i].ref_id, a[i].end) < (b[j].ref_id, b[j].end) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    let union = total(&a) + total(&b) - intersection;
+    if union == 0 {
+        return 0.0;
+    }
+    intersection as f64 / union as f64
+}
+
+/// Depth of `regions` as `(region, depth)` runs over the covered bases only, like
+/// `bedtools genomecov -bg`: sorted by `(ref_id, start)`, with neighbouring runs of equal depth
+/// coalesced. Computed by sweeping the region ends, so the cost does not depend on lengths.
+pub fn coverage(regions: &[Region]) -> Vec<(Region, u32)> {
+    let mut by_ref: BTreeMap<u64, Vec<(u64, i64)>> = BTreeMap::new();
+    for region in regions.iter().filter(|region| !region.is_empty()) {
+        let events = by_ref.entry(region.ref_id).or_default();
+        events.push((region.start, 1));
+        events.push((region.end, -1));
+    }
+    by_ref
+        .into_iter()
+        .flat_map(|(ref_id, events)| {
+            covered_runs(events)
+                .into_iter()
+                .map(move |(start, end, depth)| (Region::new(ref_id, start, end), depth))
+        })
+        .collect()
+}
+
+/// Same as `coverage`, grouped by path. Runs are plain forward regions without strand or
+/// annotations, whatever the orientation of the inputs.
+pub fn coverage_string(regions: &[StringRegion]) -> HashMap<String, Vec<(StringRegion, u32)>> {
+    let mut by_path: HashMap<&str, Vec<(u64, i64)>> = HashMap::new();
+    for region in regions.iter().filter(|region| region.start < region.end) {
+        let events = by_path.entry(&region.path).or_default();
+        events.push((region.start, 1));
+        events.push((region.end, -1));
+    }
+    by_path
+        .into_iter()
+        .map(|(path, events)| {
+            let runs = covered_runs(events)
+                .into_iter()
+                .map(|(start, end, depth)| {
+                    (StringRegion::new_inner(path.to_string(), start, end), depth)
+                })
+                .collect();
+            (path.to_string(), runs)
+        })
+        .collect()
+}
+
+/// Number of bases at each depth of coverage by `regions`, together with the number of regions
+/// that did not fit `sizes`. With `sizes`, regions are clamped to their contig, those on
+/// contigs it does not list are dropped (both count towards the returned number), and depth 0
+/// holds the uncovered rest of the genome. Without `sizes` only covered bases are reported.
+pub fn coverage_histogram(
+    regions: &[StringRegion],
+    sizes: Option<&ChromSizes>,
+) -> (BTreeMap<u32, u64>, usize) {
+    let mut clamped = 0;
+    let mut by_path: HashMap<&str, Vec<(u64, i64)>> = HashMap::new();
+    for region in regions {
+        let mut end = region.end;
+        if let Some(sizes) = sizes {
+            match sizes.get(&region.path) {
+                Some(len) if len < end => {
+                    clamped += 1;
+                    end = len;
+                }
+                Some(_) => {}
+                None => {
+                    clamped += 1;
+                    continue;
+                }
+            }
+        }
+        if region.start < end {
+            let events = by_path.entry(&region.path).or_default();
+            events.push((region.start, 1));
+            events.push((end, -1));
+        }
+    }
+    let mut histogram: BTreeMap<u32, u64> = BTreeMap::new();
+    for events in by_path.into_values() {
+        for (start, end, depth) in covered_runs(events) {
+            *histogram.entry(depth).or_default() += end - start;
+        }
+    }
+    if let Some(sizes) = sizes {
+        let genome: u64 = sizes.sizes.values().sum();
+        let covered: u64 = histogram.values().sum();
+        histogram.insert(0, genome - covered);
+    }
+    (histogram, clamped)
+}
+
+/// Fraction of the genome described by `sizes` covered at depth `min_depth` or more, with the
+/// number of regions clamped or dropped as in `coverage_histogram`. An empty genome gives 0.
+pub fn fraction_covered(
+    regions: &[StringRegion],
+    sizes: &ChromSizes,
+    min_depth: u32,
+) -> (f64, usize) {
+    let (histogram, clamped) = coverage_histogram(regions, Some(sizes));
+    let genome: u64 = histogram.values().sum();
+    if genome == 0 {
+        return (0.0, clamped);
+    }
+    let covered: u64 = histogram.range(min_depth..).map(|(_, bases)| bases).sum();
+    (covered as f64 / genome as f64, clamped)
+}
+
+/// `(start, end, depth)` runs of positive depth from `(position, +1/-1)` events.
+fn covered_runs(mut events: Vec<(u64, i64)>) -> Vec<(u64, u64, u32)> {
+    events.sort_unstable();
+    let mut runs: Vec<(u64, u64, u32)> = vec![];
+    let (mut pos, mut depth) = (0, 0i64);
+    for (at, delta) in events {
+        if depth > 0 && pos < at {
+            match runs.last_mut() {
+                Some(last) if last.1 == pos && last.2 as i64 == depth => last.1 = at,
+                _ => runs.push((pos, at, depth as u32)),
+            }
+        }
+        pos = at;
+        depth += delta;
+    }
+    runs
+}
+
+/// Every overlapping pair between `a` and `b` as `(index in a, index in b, intersection)`,
+/// found in a single sweep. Both inputs must be sorted by `(ref_id, start)`, otherwise
+/// `RegionError::Unsorted` reports the input (0 for `a`, 1 for `b`) and its first offending
+/// index. Each region is kept in an active list only while it can still overlap, so the cost
+/// is O(n + m + k) even on heavily nested input. Pairs are emitted in sweep order, i.e. by the start of the later of the two.
+pub fn intersect_sorted(
+    a: &[Region],
+    b: &[Region],
+) -> Result<Vec<(usize, usize, Region)>, RegionError> {
+    let pairs = sweep_overlaps(a, b, |r| (r.ref_id, r.start, r.end))?;
+    Ok(pairs
+        .into_iter()
+        .map(|(i, j)| (i, j, a[i].intersect(&b[j]).expect("sweep pairs overlap")))
+        .collect())
+}
+
+/// Same as `intersect_sorted` for `StringRegion`s sorted by `(path, start)`. Intersections
+/// keep the orientation, strand and annotations of the region from `a`.
+pub fn intersect_sorted_string(
+    a: &[StringRegion],
+    b: &[StringRegion],
+) -> Result<Vec<(usize, usize, StringRegion)>, RegionError> {
+    let pairs = sweep_overlaps(a, b, |r| (r.path.as_str(), r.start, r.end))?;
+    Ok(pairs
+        .into_iter()
+        .map(|(i, j)| (i, j, a[i].intersect(&b[j]).expect("sweep pairs overlap")))
+        .collect())
+}
+
+/// Sorted targets prepared for nearest-region queries. Next to the targets it keeps the
+/// running maximum of their ends on each reference, so a query binary-searches its position
+/// and walks outwards only while a closer target can still exist: to the right until the
+/// starts pass the `k`-th gap, to the left until even the largest end seen so far is farther
+/// than it. Building is O(n); reuse one index for many queries.
+#[derive(Debug, Clone)]
+pub struct NearestIndex<'a, R> {
+    targets: &'a [R],
+    max_end: Vec<u64>,
+}
+
+impl<'a> NearestIndex<'a, Region> {
+    /// `targets` must be sorted by `(ref_id, start)`.
+    pub fn new(targets: &'a [Region]) -> Self {
+        NearestIndex {
+            targets,
+            max_end: running_max_end(targets, |a, b| a.ref_id == b.ref_id),
+        }
+    }
+
+    /// Indices of the targets on the query's reference at the smallest `gap` from `query`,
+    /// ties included and in ascending order; overlapping targets are all at distance 0.
+    /// Empty when no target shares the reference.
+    pub fn closest(&self, query: &Region) -> Vec<usize> {
+        closest_of(self.k_nearest_with_ties(query, 1))
+    }
+
+    /// Up to `k` targets nearest to `query` as `(index, gap)`, by increasing gap and then
+    /// index.
+    pub fn k_nearest(&self, query: &Region, k: usize) -> Vec<(usize, u64)> {
+        let mut nearest = self.k_nearest_with_ties(query, k);
+        nearest.truncate(k);
+        nearest
+    }
+
+    fn k_nearest_with_ties(&self, query: &Region, k: usize) -> Vec<(usize, u64)> {
+        nearest_in(self.targets, &self.max_end, query, k, |t| {
+            t.ref_id.cmp(&query.ref_id)
+        })
+    }
+}
+
+impl<'a> NearestIndex<'a, StringRegion> {
+    /// `targets` must be sorted by `(path, start)`.
+    pub fn new(targets: &'a [StringRegion]) -> Self {
+        NearestIndex {
+            targets,
+            max_end: running_max_end(targets, |a, b| a.path == b.path),
+        }
+    }
+
+    /// Same as `NearestIndex::<Region>::closest`, matching targets by path.
+    pub fn closest(&self, query: &StringRegion) -> Vec<usize> {
+        closest_of(self.k_nearest_with_ties(query, 1))
+    }
+
+    /// Same as `NearestIndex::<Region>::k_nearest`, matching targets by path.
+    pub fn k_nearest(&self, query: &StringRegion, k: usize) -> Vec<(usize, u64)> {
+        let mut nearest = self.k_nearest_with_ties(query, k);
+        nearest.truncate(k);
+        nearest
+    }
+
+    fn k_nearest_with_ties(&self, query: &StringRegion, k: usize) -> Vec<(usize, u64)> {
+        nearest_in(self.targets, &self.max_end, query, k, |t| {
+            t.path.cmp(&query.path)
+        })
+    }
+}
+
+/// One-off `NearestIndex::closest`; `targets` must be sorted by `(ref_id, start)`. Build a
+/// `NearestIndex` instead to run many queries against the same targets.
+pub fn closest(query: &Region, targets: &[Region]) -> Vec<usize> {
+    NearestIndex::<Region>::new(targets).closest(query)
+}
+
+/// One-off `NearestIndex::k_nearest`; `targets` must be sorted as for `closest`.
+pub fn k_nearest(query: &Region, targets: &[Region], k: usize) -> Vec<(usize, u64)> {
+    NearestIndex::<Region>::new(targets).k_nearest(query, k)
+}
+
+/// Same as `closest` for `StringRegion`s sorted by `(path, start)`.
+pub fn closest_string(query: &StringRegion, targets: &[StringRegion]) -> Vec<usize> {
+    NearestIndex::<StringRegion>::new(targets).closest(query)
+}
+
+/// Same as `k_nearest` for `StringRegion`s sorted by `(path, start)`.
+pub fn k_nearest_string(
+    query: &StringRegion,
+    targets: &[StringRegion],
+    k: usize,
+) -> Vec<(usize, u64)> {
+    NearestIndex::<StringRegion>::new(targets).k_nearest(query, k)
+}
+
+fn closest_of(nearest: Vec<(usize, u64)>) -> Vec<usize> {
+    let best = nearest.first().map(|&(_, gap)| gap);
+    nearest
+        .into_iter()
+        .take_while(|&(_, gap)| Some(gap) == best)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// For each target, the largest end among the targets of its reference up to and including it.
+fn running_max_end<R, F>(targets: &[R], same_reference: F) -> Vec<u64>
+where
+    R: AsRange,
+    F: Fn(&R, &R) -> bool,
+{
+    let mut max_end: Vec<u64> = Vec::with_capacity(targets.len());
+    for (i, target) in targets.iter().enumerate() {
+        let end = target.bounds().1;
+        max_end.push(match max_end.last() {
+            Some(&prev) if same_reference(&targets[i - 1], target) => prev.max(end),
+            _ => end,
+        });
+    }
+    max_end
+}
+
+/// `(index, gap)` of the targets on the query's reference sorted by gap then index, holding
+/// at least the `k` nearest ones and every target tied with the last of them. `cmp_reference`
+/// orders a target's reference against the query's.
+fn nearest_in<R, F>(
+    targets: &[R],
+    max_end: &[u64],
+    query: &R,
+    k: usize,
+    cmp_reference: F,
+) -> Vec<(usize, u64)>
+where
+    R: AsRange,
+    F: Fn(&R) -> Ordering,
+{
+    if k == 0 {
+        return vec![];
+    }
+    let (start, end) = query.bounds();
+    let first = targets.partition_point(|t| cmp_reference(t) == Ordering::Less);
+    let last = targets.partition_point(|t| cmp_reference(t) != Ordering::Greater);
+    let after = first + targets[first..last].partition_point(|t| t.bounds().0 < end);
+    // Kept sorted by `(gap, index)`; only entries up to the k-th gap and its ties matter.
+    let mut nearest: Vec<(usize, u64)> = vec![];
+    let kth_gap = |nearest: &[(usize, u64)]| nearest.get(k - 1).map(|&(_, gap)| gap);
+    // From `after` on the gap is `target start - end`, growing with the index.
+    for (i, target) in targets[after..last].iter().enumerate() {
+        let gap = target.bounds().0 - end;
+        if kth_gap(&nearest).is_some_and(|kth| gap > kth) {
+            break;
+        }
+        nearest.push((after + i, gap));
+    }
+    // Before `after` the ends are unordered, but no target at or left of `i` ends past
+    // `max_end[i]`, which shrinks while walking left.
+    for i in (first..after).rev() {
+        if kth_gap(&nearest).is_some_and(|kth| start.saturating_sub(max_end[i]) > kth) {
+            break;
+        }
+        let gap = start.saturating_sub(targets[i].bounds().1);
+        if kth_gap(&nearest).is_some_and(|kth| gap > kth) {
+            continue;
+        }
+        let at = nearest.partition_point(|&(j, g)| (g, j) < (gap, i));
+        nearest.insert(at, (i, gap));
+    }
+    nearest
+}
+
+/// Access to the half-open bounds shared by `Region` and `StringRegion`.
+trait AsRange {
+    fn bounds(&self) -> (u64, u64);
+}
+
+impl AsRange for Region {
+    fn bounds(&self) -> (u64, u64) {
+        (self.start, self.end)
+    }
+}
+
+impl AsRange for StringRegion {
+    fn bounds(&self) -> (u64, u64) {
+        (self.start, self.end)
+    }
+}
+
+fn sweep_overlaps<'a, T, K, F>(
+    a: &'a [T],
+    b: &'a [T],
+    key: F,
+) -> Result<Vec<(usize, usize)>, RegionError>
+where
+    K: Ord,
+    F: Fn(&'a T) -> (K, u64, u64),
+{
+    for (input, list) in [a, b].iter().enumerate() {
+        if let Some(index) = (1..list.len()).find(|&i| {
+            let (prev, cur) = (key(&list[i - 1]), key(&list[i]));
+            (prev.0, prev.1) > (cur.0, cur.1)
+        }) {
+            return Err(RegionError::Unsorted { input, index });
+        }
+    }
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    let mut active_a: Vec<usize> = vec![];
+    let mut active_b: Vec<usize> = vec![];
+    let mut current: Option<K> = None;
+    while i < a.len() || j < b.len() {
+        let take_a = j == b.len()
+            || (i < a.len() && {
+                let (ka, kb) = (key(&a[i]), key(&b[j]));
+                (ka.0, ka.1) <= (kb.0, kb.1)
+            });
+        let (index, (reference, start, end)) = if take_a {
+            i += 1;
+            (i - 1, key(&a[i - 1]))
+        } else {
+            j += 1;
+            (j - 1, key(&b[j - 1]))
+        };
+        if current.as_ref() != Some(&reference) {
+            active_a.clear();
+            active_b.clear();
+            current = Some(reference);
+        }
+        if start == end {
+            continue;
+        }
+        let (others, others_list, own) = if take_a {
+            (&mut active_b, b, &mut active_a)
+        } else {
+            (&mut active_a, a, &mut active_b)
+        };
+        others.retain(|&k| key(&others_list[k]).2 > start);
+        for &k in others.iter() {
+            pairs.push(if take_a { (index, k) } else { (k, index) });
+        }
+        own.push(index);
+    }
+    Ok(pairs)
+}
+
+#[derive(Debug, Clone)]
+struct WindowBounds {
+    next: u64,
+    end: u64,
+    size: u64,
+    step: u64,
+    remaining: usize,
+}
+
+impl WindowBounds {
+    fn new(
+        start: u64,
+        end: u64,
+        size: u64,
+        step: u64,
+        partial: bool,
+    ) -> Result<Self, RegionParseError> {
+        if size == 0 || step == 0 {
+            return Err(RegionParseError::InvalidPosition(
+                "window size and step must be positive".to_string(),
+            ));
+        }
+        let len = end - start;
+        let count = if partial {
+            len.div_ceil(step)
+        } else if len < size {
+            0
+        } else {
+            (len - size) / step + 1
+        };
+        Ok(WindowBounds {
+            next: start,
+            end,
+            size,
+            step,
+            remaining: count as usize,
+        })
+    }
+}
+
+impl Iterator for WindowBounds {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let start = self.next;
+        self.next = self.next.saturating_add(self.step);
+        Some((start, start.saturating_add(self.size).min(self.end)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// A `Region` together with the display orientation it was written in, as returned by
+/// `Region::parse_oriented` and `Region::convert_oriented`. `region` always has
+/// `start <= end`; `reverse` records that the input was written high to low.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OrientedRegion {
+    pub region: Region,
+    pub reverse: bool,
+}
+
+/// A `Region` with a biological strand attached, as returned by `Region::into_stranded`.
+/// `Strand::Unknown` is treated as forward by the flank methods.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StrandedRegion {
+    region: Region,
+    strand: Strand,
+}
+
+impl StrandedRegion {
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
+
+    pub fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    pub fn into_region(self) -> Region {
+        self.region
+    }
+
+    /// The `len` bases before the biological start, clipped at position 0: to the left on
+    /// the forward strand, to the right on the reverse strand. The flank keeps the strand.
+    pub fn upstream(&self, len: u64) -> StrandedRegion {
+        if self.strand == Strand::Reverse {
+            self.flank_right(len)
+        } else {
+            self.flank_left(len)
+        }
+    }
+
+    /// The `len` bases after the biological end; the mirror of `upstream`.
+    pub fn downstream(&self, len: u64) -> StrandedRegion {
+        if self.strand == Strand::Reverse {
+            self.flank_left(len)
+        } else {
+            self.flank_right(len)
+        }
+    }
+
+    fn flank_left(&self, len: u64) -> StrandedRegion {
+        let start = self.region.start;
+        self.with_bounds(start.saturating_sub(len), start)
+    }
+
+    fn flank_right(&self, len: u64) -> StrandedRegion {
+        let end = self.region.end;
+        self.with_bounds(end, end.saturating_add(len))
+    }
+
+    fn with_bounds(&self, start: u64, end: u64) -> StrandedRegion {
+        StrandedRegion {
+            region: Region::new(self.region.ref_id, start, end),
+            strand: self.strand,
+        }
+    }
+}
+
+/// Iterator returned by `Region::windows` and `Region::windows_partial`.
+#[derive(Debug, Clone)]
+pub struct Windows {
+    ref_id: u64,
+    bounds: WindowBounds,
+}
+
+impl Iterator for Windows {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Region> {
+        let (start, end) = self.bounds.next()?;
+        Some(Region::new(self.ref_id, start, end))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.bounds.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Windows {}
+
+/// Iterator returned by `StringRegion::windows` and `StringRegion::windows_partial`.
+#[derive(Debug, Clone)]
+pub struct StringWindows<'a> {
+    region: &'a StringRegion,
+    bounds: WindowBounds,
+}
+
+impl<'a> Iterator for StringWindows<'a> {
+    type Item = StringRegion;
+
+    fn next(&mut self) -> Option<StringRegion> {
+        let (start, end) = self.bounds.next()?;
+        let mut window = self.region.clone();
+        window.start = start;
+        window.end = end;
+        Some(window)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.bounds.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for StringWindows<'a> {}
+
+/// Streaming counterpart of `merge_overlaps_within`: merges consecutive regions whose gap is
+/// at most `max_gap` while reading, buffering a single region. The input must already be
+/// sorted by `(ref_id, start)`; unsorted input is merged only where neighbours happen to
+/// touch.
+#[derive(Debug, Clone)]
+pub struct MergeIter<I: Iterator<Item = Region>> {
+    inner: I,
+    max_gap: u64,
+    pending: Option<Region>,
+}
+
+impl<I: Iterator<Item = Region>> MergeIter<I> {
+    pub fn new(inner: I, max_gap: u64) -> Self {
+        MergeIter {
+            inner,
+            max_gap,
+            pending: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Region>> Iterator for MergeIter<I> {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Region> {
+        let mut current = self.pending.take().or_else(|| self.inner.next())?;
+        for region in self.inner.by_ref() {
+            match current.merge_within(&region, self.max_gap) {
+                Some(hull) => current = hull,
+                None => {
+                    self.pending = Some(region);
+                    break;
+                }
+            }
+        }
+        Some(current)
+    }
+}
+
+/// Compares contig names in natural order, so that `chr2 < chr10`: runs of digits compare by
+/// numeric value, everything else byte-wise. Names equal up to leading zeros fall back to plain
+/// string order, keeping the order total.
+pub fn cmp_natural(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a.as_bytes(), b.as_bytes());
+    while let (Some(&p), Some(&q)) = (x.first(), y.first()) {
+        if p.is_ascii_digit() && q.is_ascii_digit() {
+            let (p_run, p_rest) = split_digits(x);
+            let (q_run, q_rest) = split_digits(y);
+            let ord = p_run.len().cmp(&q_run.len()).then_with(|| p_run.cmp(q_run));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            x = p_rest;
+            y = q_rest;
+        } else if p != q {
+            return p.cmp(&q);
+        } else {
+            x = &x[1..];
+            y = &y[1..];
+        }
+    }
+    x.len().cmp(&y.len()).then_with(|| a.cmp(b))
+}
+
+/// Splits a leading run of digits, without its leading zeros, from the rest of `s`.
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let len = s.iter().take_while(|c| c.is_ascii_digit()).count();
+    let (run, rest) = s.split_at(len);
+    let zeros = run.iter().take_while(|&&c| c == b'0').count();
+    (&run[zeros..], rest)
+}
+
+/// Streams several sources, each sorted by `(path, start)` with paths in `cmp_natural` order,
+/// as one sorted sequence. Regions with equal keys come out in source order.
+pub fn merge_sorted<I: Iterator<Item = StringRegion>>(sources: Vec<I>) -> MergeSorted<I> {
+    MergeSorted::new(sources)
+}
+
+/// Same as `merge_sorted`, but checks each source while reading. A region sorting before its
+/// predecessor in the same source yields `RegionError::Unsorted` with the position of that
+/// source as `input` and the region's index in it, after which the iterator ends.
+pub fn merge_sorted_checked<I: Iterator<Item = StringRegion>>(
+    sources: Vec<I>,
+) -> CheckedMergeSorted<I> {
+    CheckedMergeSorted {
+        inner: MergeSorted::new(sources),
+        error: None,
+        done: false,
+    }
+}
+
+fn cmp_merge_key(a: &StringRegion, b: &StringRegion) -> Ordering {
+    cmp_natural(&a.path, &b.path).then(a.start.cmp(&b.start))
+}
+
+#[derive(Debug)]
+struct MergeHead {
+    region: StringRegion,
+    source: usize,
+}
+
+impl PartialEq for MergeHead {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeHead {}
+
+impl PartialOrd for MergeHead {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeHead {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_merge_key(&self.region, &other.region).then(self.source.cmp(&other.source))
+    }
+}
+
+/// Iterator returned by `merge_sorted`: a k-way merge holding one region per source in a
+/// binary heap.
+#[derive(Debug)]
+pub struct MergeSorted<I: Iterator<Item = StringRegion>> {
+    sources: Vec<I>,
+    heads: BinaryHeap<Reverse<MergeHead>>,
+    read: Vec<usize>,
+}
+
+impl<I: Iterator<Item = StringRegion>> MergeSorted<I> {
+    fn new(mut sources: Vec<I>) -> Self {
+        let mut heads = BinaryHeap::with_capacity(sources.len());
+        for (source, iter) in sources.iter_mut().enumerate() {
+            if let Some(region) = iter.next() {
+                heads.push(Reverse(MergeHead { region, source }));
+            }
+        }
+        let read = vec![1; sources.len()];
+        MergeSorted {
+            sources,
+            heads,
+            read,
+        }
+    }
+
+    /// Pops the smallest head and refills from its source. The error reports a refill that
+    /// sorts before the popped region.
+    fn advance(&mut self) -> Option<(StringRegion, Result<(), RegionError>)> {
+        let Reverse(head) = self.heads.pop()?;
+        let mut order = Ok(());
+        if let Some(region) = self.sources[head.source].next() {
+            if cmp_merge_key(&region, &head.region) == Ordering::Less {
+                order = Err(RegionError::Unsorted {
+                    input: head.source,
+                    index: self.read[head.source],
+                });
+            }
+            self.read[head.source] += 1;
+            self.heads.push(Reverse(MergeHead {
+                region,
+                source: head.source,
+            }));
+        }
+        Some((head.region, order))
+    }
+}
+
+impl<I: Iterator<Item = StringRegion>> Iterator for MergeSorted<I> {
+    type Item = StringRegion;
+
+    fn next(&mut self) -> Option<StringRegion> {
+        self.advance().map(|(region, _)| region)
+    }
+}
+
+/// Iterator returned by `merge_sorted_checked`.
+#[derive(Debug)]
+pub struct CheckedMergeSorted<I: Iterator<Item = StringRegion>> {
+    inner: MergeSorted<I>,
+    error: Option<RegionError>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = StringRegion>> Iterator for CheckedMergeSorted<I> {
+    type Item = Result<StringRegion, RegionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(error) = self.error.take() {
+            self.done = true;
+            return Some(Err(error));
+        }
+        let (region, order) = self.inner.advance()?;
+        self.error = order.err();
+        Some(Ok(region))
+    }
+}
+
+/// `region + len` is `region.pad(len, len)`: both ends move outwards, `start` saturating at 0.
+impl Add<u64> for Region {
+    type Output = Region;
+    fn add(self, len: u64) -> Region {
+        self.pad(len, len)
+    }
+}
+
+/// `region - len` is `region.shrink(len)`: both ends move inwards, collapsing at the midpoint.
+impl Sub<u64> for Region {
+    type Output = Region;
+    fn sub(self, len: u64) -> Region {
+        self.shrink(len)
+    }
+}
+
+/// `region << len` moves the region `len` bases towards 0, saturating like `shift`.
+impl Shl<u64> for Region {
+    type Output = Region;
+    fn shl(self, len: u64) -> Region {
+        Region::new(
+            self.ref_id,
+            self.start.saturating_sub(len),
+            self.end.saturating_sub(len),
+        )
+    }
+}
+
+/// `region >> len` moves the region `len` bases away from 0, saturating like `shift`.
+impl Shr<u64> for Region {
+    type Output = Region;
+    fn shr(self, len: u64) -> Region {
+        Region::new(
+            self.ref_id,
+            self.start.saturating_add(len),
+            self.end.saturating_add(len),
+        )
+    }
+}
+
+impl From<Region> for (u64, u64, u64) {
+    fn from(region: Region) -> Self {
+        (region.ref_id, region.start, region.end)
+    }
+}
+
+impl TryFrom<(u64, u64, u64)> for Region {
+    type Error = RegionParseError;
+
+    /// Builds a region from `(ref_id, start, end)`, rejecting `start > end`.
+    fn try_from((ref_id, start, end): (u64, u64, u64)) -> Result<Self, Self::Error> {
+        if start > end {
+            return Err(RegionParseError::InvalidPosition(format!(
+                "start should not be greater than end ({} > {})",
+                start, end
+            )));
+        }
+        Ok(Region { ref_id, start, end })
+    }
+}
+
+/// Serde helpers writing `Region` coordinates 1-based closed instead of the crate's native
+/// 0-based half-open, for `#[serde(with = "genomic_range::one_based")]`. Deserializing
+/// applies the inverse conversion.
+#[cfg(feature = "serde")]
+pub mod one_based {
+    use super::Region;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct OneBased {
+        ref_id: u64,
+        start: u64,
+        end: u64,
+    }
+
+    pub fn serialize<S: Serializer>(region: &Region, serializer: S) -> Result<S::Ok, S::Error> {
+        OneBased {
+            ref_id: region.ref_id,
+            start: region.start + 1,
+            end: region.end,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Region, D::Error> {
+        let raw = OneBased::deserialize(deserializer)?;
+        if raw.start == 0 || raw.start - 1 > raw.end {
+            return Err(D::Error::custom(format!(
+                "invalid 1-based region: {}-{}",
+                raw.start, raw.end
+            )));
+        }
+        Ok(Region {
+            ref_id: raw.ref_id,
+            start: raw.start - 1,
+            end: raw.end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_region_new_with_prefix_rewrites_path() {
+        let region = StringRegion::new_with_prefix("chr1:100-200".to_string(), "").unwrap();
+        assert_eq!(region.path, "1");
+        assert_eq!((region.start(), region.end()), (100, 200));
+        let region = StringRegion::new_with_prefix("1:100-200".to_string(), "chr").unwrap();
+        assert_eq!(region.path, "chr1");
+        let region = StringRegion::new_with_prefix("chr1:1-2:+".to_string(), "chr").unwrap();
+        assert_eq!(region, StringRegion::new("chr1:1-2:+").unwrap());
+    }
+
+    #[test]
+    fn string_region_extend_stops_at_zero() {
+        let mut region = StringRegion::new("chr1:100-200").unwrap();
+        region.extend(150);
+        assert_eq!((region.start(), region.end()), (0, 350));
+    }
+
+    fn region_format(path: &str) -> String {
+        format!("{}", StringRegion::new(path).unwrap())
+    }
+
+    #[test]
+    fn region_works() {
+        assert_eq!(StringRegion::new("").ok(), None);
+        assert_eq!(StringRegion::new(":10-20").ok(), None);
+        assert_eq!(
+            StringRegion::new("chr1:12000-12001").ok(),
+            Some(StringRegion {
+                path: "chr1".to_string(),
+                start: 12000,
+                end: 12001,
+                inverted: false,
+                strand: None,
+                name: None,
+                score: None,
+                system: None,
+            })
+        );
+        assert_eq!(
+            StringRegion::new("chr1:1200943-1201000").ok(),
+            Some(StringRegion {
+                path: "chr1".to_string(),
+                start: 1200943,
+                end: 1201000,
+                inverted: false,
+                strand: None,
+                name: None,
+                score: None,
+                system: None,
+            })
+        );
+    }
+
+    #[test]
+    fn region_format_works() {
+        let a = "chr1:12000-12001";
+        assert_eq!(region_format(a), a);
+        let b = "10:120-120001";
+        assert_eq!(region_format(b), b);
+    }
+
+    #[test]
+    fn optional_region_resolve_works() {
+        let lens = |path: &str| if path == "chr1" { Some(1000) } else { None };
+        let full = OptionalRegion::new("chr1:100-200").unwrap();
+        assert_eq!(
+            full.resolve(lens).unwrap(),
+            StringRegion::new("chr1:100-200").unwrap()
+        );
+        let no_end = OptionalRegion::new("chr1:100").unwrap();
+        assert_eq!(
+            no_end.resolve(lens).unwrap(),
+            StringRegion::new("chr1:100-1000").unwrap()
+        );
+        let no_start = OptionalRegion {
+            path: "chr1".to_string(),
+            start: None,
+            end: Some(500),
+        };
+        assert_eq!(
+            no_start.resolve(lens).unwrap(),
+            StringRegion::new("chr1:0-500").unwrap()
+        );
+        let bare = OptionalRegion {
+            path: "chr1".to_string(),
+            start: None,
+            end: None,
+        };
+        assert_eq!(
+            bare.resolve(lens).unwrap(),
+            StringRegion::new("chr1:0-1000").unwrap()
+        );
+        let unknown = OptionalRegion::new("chr2:100").unwrap();
+        assert_eq!(
+            unknown.resolve(lens),
+            Err(RegionParseError::UnknownContig("chr2".to_string()))
+        );
+        let unknown_full = OptionalRegion::new("chr2:100-200").unwrap();
+        assert_eq!(
+            unknown_full.resolve(lens).unwrap(),
+            StringRegion::new("chr2:100-200").unwrap()
+        );
+        let past_end = OptionalRegion::new("chr1:2000").unwrap();
+        assert!(matches!(
+            past_end.resolve(lens),
+            Err(RegionParseError::InvalidPosition(_))
+        ));
+        let at_end = OptionalRegion::new("chr1:1000").unwrap();
+        assert_eq!(
+            at_end.resolve(lens).unwrap(),
+            StringRegion::new("chr1:1000-1000").unwrap()
+        );
+    }
+
+    #[test]
+    fn strand_suffix_round_trip() {
+        let a = StringRegion::new("chr1:100-200:+").unwrap();
+        assert_eq!(a.strand(), Some(Strand::Forward));
+        assert_eq!(a.start(), 100);
+        assert_eq!(a.end(), 200);
+        assert_eq!(region_format("chr1:100-200:+"), "chr1:100-200:+");
+        let b = StringRegion::new("chr1:200-100:-").unwrap();
+        assert!(b.inverted());
+        assert_eq!(b.strand(), Some(Strand::Reverse));
+        assert_eq!(region_format("chr1:200-100:-"), "chr1:200-100:-");
+        assert_eq!(StringRegion::new("chr1:100-200").unwrap().strand(), None);
+        assert!(StringRegion::new("chr1:100-200:x").is_err());
+    }
+
+    #[test]
+    fn strand_bed_round_trip() {
+        let fields = ["chr2", "10", "50", ".", "0", "-"];
+        let region = StringRegion::from_bed_fields(&fields).unwrap();
+        assert_eq!(region.strand(), Some(Strand::Reverse));
+        assert_eq!(region.to_bed_fields(), fields);
+        let mut plain = StringRegion::from_bed_fields(&["chr2", "10", "50"]).unwrap();
+        assert_eq!(plain.strand(), None);
+        assert_eq!(plain.to_bed_fields(), vec!["chr2", "10", "50"]);
+        plain.set_strand(Some(Strand::Unknown));
+        assert_eq!(
+            plain.to_bed_fields(),
+            vec!["chr2", "10", "50", ".", ".", "."]
+        );
+    }
+
+    #[test]
+    fn name_bed_round_trip() {
+        let fields = ["chr1", "100", "200", "peak_1", "0", "+"];
+        let mut region = StringRegion::from_bed_fields(&fields).unwrap();
+        assert_eq!(region.name(), Some("peak_1"));
+        assert_eq!(format!("{}", region), "chr1:100-200:+");
+        region.extend(10);
+        assert_eq!(
+            region.to_bed_fields(),
+            vec!["chr1", "90", "210", "peak_1", "0", "+"]
+        );
+        let bed4 = StringRegion::from_bed_fields(&["chr1", "100", "200", "peak_2"]).unwrap();
+        assert_eq!(bed4.to_bed_fields(), vec!["chr1", "100", "200", "peak_2"]);
+        let unnamed = StringRegion::from_bed_fields(&["chr1", "100", "200", "."]).unwrap();
+        assert_eq!(unnamed.name(), None);
+        assert_eq!(bed4, unnamed);
+        assert!(!bed4.eq_with_name(&unnamed));
+        assert!(bed4.eq_with_name(&bed4.clone()));
+    }
+
+    #[test]
+    fn strand_gff_fields() {
+        let fields = ["chr3", "src", "exon", "101", "200", ".", "+"];
+        let region = StringRegion::from_gff_fields(&fields).unwrap();
+        assert_eq!(region.start(), 100);
+        assert_eq!(region.end(), 200);
+        assert_eq!(region.strand(), Some(Strand::Forward));
+    }
+
+    #[test]
+    fn region_cmp_by_coordinate_sorts() {
+        let mut regions = vec![
+            Region::new(3, 50, 60),
+            Region::new(3, 10, 30),
+            Region::new(3, 10, 20),
+            Region::new(3, 0, 100),
+        ];
+        regions.sort_by(Region::cmp_by_coordinate);
+        assert_eq!(
+            regions,
+            vec![
+                Region::new(3, 0, 100),
+                Region::new(3, 10, 20),
+                Region::new(3, 10, 30),
+                Region::new(3, 50, 60),
+            ]
+        );
+        assert_eq!(
+            Region::new(0, 5, 10).cmp_by_coordinate(&Region::new(1, 5, 10)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn score_bed_fields() {
+        let a = StringRegion::from_bed_fields(&["chr1", "0", "10", "a", "12"]).unwrap();
+        let b = StringRegion::from_bed_fields(&["chr1", "0", "10", "b", "8.5"]).unwrap();
+        let c = StringRegion::from_bed_fields(&["chr1", "0", "10", "c", "."]).unwrap();
+        let d = StringRegion::from_bed_fields(&["chr1", "0", "10", "d"]).unwrap();
+        assert_eq!(a.score(), Some(12.0));
+        assert_eq!(b.score(), Some(8.5));
+        assert_eq!(c.score(), None);
+        assert_eq!(d.score(), None);
+        assert!(StringRegion::from_bed_fields(&["chr1", "0", "10", "e", "high"]).is_err());
+        assert_eq!(b.to_bed_fields(), vec!["chr1", "0", "10", "b", "8.5"]);
+
+        let mut regions = vec![c.clone(), b.clone(), d.clone(), a.clone()];
+        sort_by_score_desc(&mut regions);
+        let names: Vec<_> = regions.iter().map(|r| r.name().unwrap()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        let kept = filter_by_score(regions, 10.0);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].name(), Some("a"));
+    }
+
+    #[test]
+    fn score_sort_with_nan_and_missing_dot() {
+        let scored = |name: &str, score: &str| {
+            StringRegion::from_bed_fields(&["chr1", "0", "10", name, score]).unwrap()
+        };
+        let mut regions = vec![
+            scored("nan1", "NaN"),
+            scored("low", "1"),
+            scored("none", "."),
+            scored("nan2", "NaN"),
+            scored("high", "39"),
+            scored("mid", "7.5"),
+        ];
+        sort_by_score_desc(&mut regions);
+        let names: Vec<_> = regions.iter().map(|r| r.name().unwrap()).collect();
+        assert_eq!(names, vec!["high", "mid", "low", "nan1", "nan2", "none"]);
+
+        let fields = ["chr1", "0", "10", "x", ".", "+"];
+        let region = StringRegion::from_bed_fields(&fields).unwrap();
+        assert_eq!(region.score(), None);
+        assert_eq!(region.to_bed_fields(), fields);
+        assert_eq!(region.to_bed_fields_with_clamp(true), fields);
+    }
+
+    #[test]
+    fn score_clamping() {
+        let mut region = StringRegion::from_bed_fields(&["chr1", "0", "10", "x", "2500"]).unwrap();
+        assert_eq!(region.to_bed_fields()[4], "2500");
+        assert_eq!(region.to_bed_fields_with_clamp(true)[4], "1000");
+        region.set_score(Some(-3.0));
+        assert_eq!(region.to_bed_fields_with_clamp(true)[4], "0");
+        region.set_score(Some(512.5));
+        assert_eq!(region.to_bed_fields_with_clamp(true)[4], "512.5");
+    }
+
+    #[test]
+    fn borrowed_region_matches_eager_parser() {
+        for input in &[
+            "chr1:12000-12001",
+            "chr1:200-100",
+            "chr1:100-200:+",
+            "chr1:200-100:-",
+            "HLA-A*01:01:1-20",
+            "chr2 10 50",
+            "chr2\t50\t10",
+        ] {
+            let borrowed = BorrowedRegion::parse(input).unwrap();
+            assert_eq!(borrowed.into_owned(), StringRegion::new(input).unwrap());
+        }
+        let line = String::from("chrX:5-9");
+        let borrowed = BorrowedRegion::parse(&line).unwrap();
+        assert_eq!(borrowed.path.as_ptr(), line.as_ptr());
+        for input in &["", ":10-20", "chr1", "chr1:10", "chr1:a-20", "chr1:10-20:x"] {
+            assert!(BorrowedRegion::parse(input).is_err(), "{}", input);
+            assert!(StringRegion::new(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn region_with_merge_combines_payloads() {
+        let regions = vec![
+            RegionWith::new(StringRegion::new("chr1:50-80").unwrap(), 2),
+            RegionWith::new(StringRegion::new("chr1:10-30").unwrap(), 1),
+            RegionWith::new(StringRegion::new("chr2:10-30").unwrap(), 7),
+            RegionWith::new(StringRegion::new("chr1:70-60").unwrap(), 3),
+            RegionWith::new(StringRegion::new("chr1:30-40").unwrap(), 4),
+        ];
+        let merged = merge_with(regions, |a, b| a + b);
+        let summary: Vec<_> = merged.iter().map(|r| (r.uuid(), r.data)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("chr1:10-40".to_string(), 5),
+                ("chr1:50-80".to_string(), 5),
+                ("chr2:10-30".to_string(), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn region_with_derefs_and_reads_bed() {
+        let fields = ["chr1", "5", "15", "x", "3", "+", "extra1", "extra2"];
+        let mut record = RegionWith::from_bed_fields(&fields).unwrap();
+        assert_eq!(record.data, vec!["extra1", "extra2"]);
+        assert_eq!(record.strand(), Some(Strand::Forward));
+        assert_eq!(record.interval(), 10);
+        record.extend(5);
+        assert_eq!(record.start(), 0);
+        let mut records = vec![
+            record,
+            RegionWith::from_bed_fields(&["chr1", "0", "1", "y", "9"]).unwrap(),
+        ];
+        sort_by_score_desc(&mut records);
+        assert_eq!(records[0].name(), Some("y"));
+        assert_eq!(filter_by_score(records, 5.0).len(), 1);
+    }
+
+    #[test]
+    fn region_shift_checked() {
+        let region = Region::new(1, 100, 200);
+        assert_eq!(region.shift_checked(0), Some(region.clone()));
+        assert_eq!(region.shift_checked(50), Some(Region::new(1, 150, 250)));
+        assert_eq!(region.shift_checked(-100), Some(Region::new(1, 0, 100)));
+        assert_eq!(region.shift_checked(-101), None);
+        assert_eq!(region.shift_checked(i64::MIN), None);
+        let high = Region::new(1, u64::MAX - 10, u64::MAX - 5);
+        assert_eq!(
+            high.shift_checked(5),
+            Some(Region::new(1, u64::MAX - 5, u64::MAX))
+        );
+        assert_eq!(high.shift_checked(6), None);
+        assert_eq!(high.shift_checked(i64::MAX), None);
+    }
+
+    #[test]
+    fn tagged_region_round_trip() {
+        let input = "chr1:100-200;id=peak_17;fold=8.2;expr=a=b";
+        let mut tagged = TaggedRegion::new(input).unwrap();
+        assert_eq!(tagged.region, StringRegion::new("chr1:100-200").unwrap());
+        assert_eq!(tagged.tag("id"), Some("peak_17"));
+        assert_eq!(tagged.tag("fold"), Some("8.2"));
+        assert_eq!(tagged.tag("expr"), Some("a=b"));
+        assert_eq!(tagged.tag("missing"), None);
+        assert_eq!(format!("{}", tagged), input);
+        tagged.set_tag("fold", "9").unwrap();
+        tagged.set_tag("new", "1").unwrap();
+        assert_eq!(
+            format!("{}", tagged),
+            "chr1:100-200;id=peak_17;fold=9;expr=a=b;new=1"
+        );
+        assert!(tagged.set_tag("bad", "x;y").is_err());
+        assert!(tagged.set_tag("", "x").is_err());
+
+        let plain = TaggedRegion::new("chr2:5-1").unwrap();
+        assert!(plain.tags().is_empty());
+        assert!(plain.inverted());
+        assert_eq!(format!("{}", plain), "chr2:5-1");
+    }
+
+    #[test]
+    fn tagged_region_rejects_invalid() {
+        assert!(TaggedRegion::new("chr1:100-200;id=a;id=b").is_err());
+        assert!(TaggedRegion::new("chr1:100-200;novalue").is_err());
+        assert!(TaggedRegion::new("chr1:100-200;").is_err());
+        assert!(TaggedRegion::new("chr1;id=a").is_err());
+        assert!(TaggedRegion::new(";id=a").is_err());
+    }
+
+    #[test]
+    fn parse_infer_detects_prefix() {
+        let (region, had_chr_prefix) = StringRegion::parse_infer("chr1:1-2").unwrap();
+        assert!(had_chr_prefix);
+        assert_eq!(region, StringRegion::new("1:1-2").unwrap());
+        let (region, had_chr_prefix) = StringRegion::parse_infer("1:1-2").unwrap();
+        assert!(!had_chr_prefix);
+        assert_eq!(region.path, "1");
+        assert!(StringRegion::parse_infer("chr1").is_err());
+    }
+
+    #[test]
+    fn region_overlaps() {
+        let a = Region::new(0, 10, 20);
+        assert!(!a.overlaps(&Region::new(0, 20, 30)));
+        assert!(!a.overlaps(&Region::new(0, 0, 10)));
+        assert!(a.overlaps(&Region::new(0, 19, 30)));
+        assert!(a.overlaps(&Region::new(0, 12, 15)));
+        assert!(Region::new(0, 12, 15).overlaps(&a));
+        assert!(a.overlaps(&a.clone()));
+        assert!(!a.overlaps(&Region::new(0, 30, 40)));
+        assert!(!a.overlaps(&Region::new(1, 10, 20)));
+        assert!(a.overlaps_range(0, 11));
+        assert!(!a.overlaps_range(0, 10));
+        assert!(!a.overlaps_range(15, 15));
+    }
+
+    #[test]
+    fn region_mask() {
+        let target = Region::new(0, 0, 100);
+        let masks = vec![
+            Region::new(0, 40, 60),
+            Region::new(0, 20, 50),
+            Region::new(1, 0, 100),
+            Region::new(0, 90, 120),
+        ];
+        assert_eq!(
+            target.mask(&masks),
+            vec![Region::new(0, 0, 20), Region::new(0, 60, 90)]
+        );
+        assert_eq!(target.mask(&[]), vec![target.clone()]);
+        assert_eq!(target.mask(&[Region::new(0, 0, 100)]), vec![]);
+    }
+
+    #[test]
+    fn region_intersect() {
+        let a = Region::new(0, 10, 50);
+        assert_eq!(
+            a.intersect(&Region::new(0, 20, 30)),
+            Some(Region::new(0, 20, 30))
+        );
+        assert_eq!(
+            a.intersect(&Region::new(0, 40, 80)),
+            Some(Region::new(0, 40, 50))
+        );
+        assert_eq!(
+            a.intersect(&Region::new(0, 0, 15)),
+            Some(Region::new(0, 10, 15))
+        );
+        assert_eq!(a.intersect(&Region::new(0, 50, 80)), None);
+        assert_eq!(
+            a.intersect_allow_empty(&Region::new(0, 50, 80)),
+            Some(Region::new(0, 50, 50))
+        );
+        assert_eq!(a.intersect(&Region::new(0, 60, 80)), None);
+        assert_eq!(a.intersect_allow_empty(&Region::new(0, 60, 80)), None);
+        assert_eq!(a.intersect(&Region::new(1, 20, 30)), None);
+    }
+
+    #[test]
+    fn string_region_intersect() {
+        let a = StringRegion::new("chr1:50-10").unwrap();
+        let b = StringRegion::new("chr1:20-30").unwrap();
+        let nested = a.intersect(&b).unwrap();
+        assert!(nested.inverted());
+        assert_eq!(format!("{}", nested), "chr1:30-20");
+        assert_eq!(format!("{}", b.intersect(&a).unwrap()), "chr1:20-30");
+        let partial = StringRegion::new("chr1:40-80").unwrap();
+        assert_eq!(format!("{}", a.intersect(&partial).unwrap()), "chr1:50-40");
+        let touching = StringRegion::new("chr1:50-80").unwrap();
+        assert_eq!(a.intersect(&touching), None);
+        assert_eq!(
+            format!("{}", a.intersect_allow_empty(&touching).unwrap()),
+            "chr1:50-50"
+        );
+        assert_eq!(a.intersect(&StringRegion::new("chr1:60-80").unwrap()), None);
+        let other_name = StringRegion::new("1:20-30").unwrap();
+        assert_eq!(a.intersect(&other_name), None);
+        let strip = |p: &str| p.trim_start_matches("chr").to_string();
+        assert_eq!(
+            a.intersect_by(&other_name, |x, y| strip(x) == strip(y)),
+            Some(nested)
+        );
+    }
+
+    #[test]
+    fn region_tuple_conversions() {
+        let (ref_id, start, end) = Region::new(2, 10, 20).into();
+        assert_eq!((ref_id, start, end), (2, 10, 20));
+        assert_eq!(Region::try_from((2, 10, 20)), Ok(Region::new(2, 10, 20)));
+        assert_eq!(Region::try_from((2, 10, 10)), Ok(Region::new(2, 10, 10)));
+        assert!(matches!(
+            Region::try_from((2, 20, 10)),
+            Err(RegionParseError::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn region_union_and_hull() {
+        let a = Region::new(0, 10, 20);
+        assert_eq!(
+            a.union(&Region::new(0, 15, 30)),
+            Some(Region::new(0, 10, 30))
+        );
+        assert_eq!(
+            a.union(&Region::new(0, 20, 30)),
+            Some(Region::new(0, 10, 30))
+        );
+        assert_eq!(a.union(&Region::new(0, 0, 10)), Some(Region::new(0, 0, 20)));
+        assert_eq!(a.union(&Region::new(0, 21, 30)), None);
+        assert_eq!(a.union(&Region::new(1, 15, 30)), None);
+        assert_eq!(
+            a.hull(&Region::new(0, 21, 30)),
+            Some(Region::new(0, 10, 30))
+        );
+        assert_eq!(a.hull(&Region::new(1, 21, 30)), None);
+    }
+
+    #[test]
+    fn string_region_union_and_hull() {
+        let a = StringRegion::new("chr1:20-10").unwrap();
+        let adjacent = StringRegion::new("chr1:20-30").unwrap();
+        assert_eq!(format!("{}", a.union(&adjacent).unwrap()), "chr1:30-10");
+        assert_eq!(format!("{}", adjacent.union(&a).unwrap()), "chr1:10-30");
+        let gapped = StringRegion::new("chr1:21-30").unwrap();
+        assert_eq!(a.union(&gapped), None);
+        assert_eq!(format!("{}", a.hull(&gapped).unwrap()), "chr1:30-10");
+        let other = StringRegion::new("chr2:15-30").unwrap();
+        assert_eq!(a.union(&other), None);
+        assert_eq!(a.hull(&other), None);
+    }
+
+    #[test]
+    fn string_region_canonical() {
+        let a = StringRegion::new("chr1:200-100").unwrap();
+        let b = StringRegion::new("1:100-200").unwrap();
+        assert_eq!(a.canonical(), "1:100-200");
+        assert_eq!(a.canonical(), b.canonical());
+        assert_eq!(
+            StringRegion::new("chr1:100-200:+").unwrap().canonical(),
+            b.canonical()
+        );
+        assert_ne!(
+            StringRegion::new("chr2:100-200").unwrap().canonical(),
+            b.canonical()
+        );
+    }
+
+    #[test]
+    fn region_subtract() {
+        let a = Region::new(0, 10, 50);
+        assert_eq!(a.subtract(&Region::new(0, 60, 70)), vec![a.clone()]);
+        assert_eq!(a.subtract(&Region::new(0, 50, 70)), vec![a.clone()]);
+        assert_eq!(a.subtract(&Region::new(1, 20, 30)), vec![a.clone()]);
+        assert_eq!(a.subtract(&Region::new(0, 0, 60)), vec![]);
+        assert_eq!(a.subtract(&a.clone()), vec![]);
+        assert_eq!(
+            a.subtract(&Region::new(0, 0, 20)),
+            vec![Region::new(0, 20, 50)]
+        );
+        assert_eq!(
+            a.subtract(&Region::new(0, 40, 60)),
+            vec![Region::new(0, 10, 40)]
+        );
+        assert_eq!(
+            a.subtract(&Region::new(0, 20, 30)),
+            vec![Region::new(0, 10, 20), Region::new(0, 30, 50)]
+        );
+        assert_eq!(a.subtract(&Region::new(0, 20, 20)), vec![a.clone()]);
+        assert_eq!(Region::new(0, 20, 20).subtract(&a), vec![]);
+        assert_eq!(Region::new(0, 5, 5).subtract(&Region::new(0, 0, 1)), vec![]);
+    }
+
+    #[test]
+    fn string_region_subtract() {
+        let a = StringRegion::new("chr1:50-10").unwrap();
+        let show = |v: Vec<StringRegion>| v.iter().map(|r| r.uuid()).collect::<Vec<_>>();
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr1:60-70").unwrap())),
+            vec!["chr1:50-10"]
+        );
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr2:20-30").unwrap())),
+            vec!["chr1:50-10"]
+        );
+        assert!(a
+            .subtract(&StringRegion::new("chr1:0-60").unwrap())
+            .is_empty());
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr1:0-20").unwrap())),
+            vec!["chr1:50-20"]
+        );
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr1:40-60").unwrap())),
+            vec!["chr1:40-10"]
+        );
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr1:20-30").unwrap())),
+            vec!["chr1:20-10", "chr1:50-30"]
+        );
+        assert_eq!(
+            show(a.subtract(&StringRegion::new("chr1:20-20").unwrap())),
+            vec!["chr1:50-10"]
+        );
+        let empty = StringRegion::new("chr1:5-5").unwrap();
+        assert!(empty
+            .subtract(&StringRegion::new("chr1:0-1").unwrap())
+            .is_empty());
+        assert!(empty.subtract(&a).is_empty());
+    }
+
+    #[test]
+    fn optional_region_signed_interval() {
+        assert_eq!(
+            OptionalRegion::new("chr1:100-250")
+                .unwrap()
+                .signed_interval(),
+            Some(150)
+        );
+        assert_eq!(
+            OptionalRegion::new("chr1:250-100")
+                .unwrap()
+                .signed_interval(),
+            Some(-150)
+        );
+        assert_eq!(
+            OptionalRegion::new("chr1:100-100")
+                .unwrap()
+                .signed_interval(),
+            Some(0)
+        );
+        assert_eq!(
+            OptionalRegion::new("chr1:100").unwrap().signed_interval(),
+            None
+        );
+        let huge = OptionalRegion {
+            path: "chr1".to_string(),
+            start: Some(0),
+            end: Some(u64::MAX),
+        };
+        assert_eq!(huge.signed_interval(), None);
+        assert_eq!(huge.interval(), Some(u64::MAX));
+        let max = OptionalRegion {
+            path: "chr1".to_string(),
+            start: Some(i64::MAX as u64),
+            end: Some(0),
+        };
+        assert_eq!(max.signed_interval(), Some(-i64::MAX));
+        let past = OptionalRegion {
+            start: Some(i64::MAX as u64 + 1),
+            ..max
+        };
+        assert_eq!(past.signed_interval(), None);
+    }
+
+    #[test]
+    fn region_merge_within() {
+        let a = Region::new(0, 10, 20);
+        assert_eq!(
+            a.merge_within(&Region::new(0, 25, 30), 5),
+            Some(Region::new(0, 10, 30))
+        );
+        assert_eq!(a.merge_within(&Region::new(0, 25, 30), 4), None);
+        assert_eq!(
+            a.merge_within(&Region::new(0, 0, 5), 5),
+            Some(Region::new(0, 0, 20))
+        );
+        assert_eq!(
+            a.merge_within(&Region::new(0, 20, 30), 0),
+            a.union(&Region::new(0, 20, 30))
+        );
+        assert_eq!(a.merge_within(&Region::new(1, 20, 30), 100), None);
+
+        let chain: Vec<Region> = (0..5)
+            .map(|i| Region::new(0, i * 15, i * 15 + 10))
+            .collect();
+        let mut regions: Vec<Region> = chain.iter().rev().cloned().collect();
+        regions.push(Region::new(1, 0, 10));
+        merge_overlaps_within(&mut regions, 5);
+        assert_eq!(regions, vec![Region::new(0, 0, 70), Region::new(1, 0, 10)]);
+        let mut regions = chain.clone();
+        merge_overlaps_within(&mut regions, 4);
+        assert_eq!(regions, chain);
+    }
+
+    #[test]
+    fn region_contains_closed() {
+        let outer = Region::new(0, 10, 20);
+        let touching = Region::new(0, 15, 20);
+        assert!(outer.contains_closed(&touching));
+        assert!(outer.include(&touching));
+        assert!(outer.contains_closed(&outer.clone()));
+        assert!(outer.contains_closed(&Region::new(0, 10, 15)));
+        assert!(outer.include(&Region::new(0, 10, 15)));
+        assert!(!outer.contains_closed(&Region::new(0, 15, 21)));
+        assert!(!outer.contains_closed(&Region::new(1, 15, 20)));
+    }
+
+    #[test]
+    fn region_distance() {
+        let a = Region::new(0, 100, 200);
+        assert_eq!(a.distance(&Region::new(0, 250, 300)), Some(50));
+        assert_eq!(a.distance(&Region::new(0, 20, 60)), Some(-40));
+        assert_eq!(a.distance(&Region::new(0, 150, 300)), Some(0));
+        assert_eq!(a.distance(&Region::new(0, 200, 300)), Some(0));
+        assert_eq!(a.distance(&Region::new(0, 201, 300)), Some(1));
+        assert_eq!(a.distance(&Region::new(0, 0, 100)), Some(0));
+        assert_eq!(a.distance(&Region::new(1, 250, 300)), None);
+        assert_eq!(a.gap(&Region::new(0, 20, 60)), Some(40));
+        assert_eq!(a.gap(&Region::new(1, 20, 60)), None);
+        let far = Region::new(0, u64::MAX - 1, u64::MAX);
+        assert_eq!(Region::new(0, 0, 0).gap(&far), Some(u64::MAX - 1));
+        assert_eq!(Region::new(0, 0, 0).distance(&far), None);
+    }
+
+    #[test]
+    fn string_region_distance() {
+        let a = StringRegion::new("chr1:200-100").unwrap();
+        assert_eq!(
+            a.distance(&StringRegion::new("chr1:250-300").unwrap()),
+            Some(50)
+        );
+        assert_eq!(
+            a.distance(&StringRegion::new("chr1:60-20").unwrap()),
+            Some(-40)
+        );
+        assert_eq!(
+            a.distance(&StringRegion::new("chr1:150-300").unwrap()),
+            Some(0)
+        );
+        assert_eq!(
+            a.distance(&StringRegion::new("chr1:200-300").unwrap()),
+            Some(0)
+        );
+        assert_eq!(
+            a.distance(&StringRegion::new("chr2:250-300").unwrap()),
+            None
+        );
+        assert_eq!(a.gap(&StringRegion::new("chr1:60-20").unwrap()), Some(40));
+    }
+
+    #[test]
+    fn parse_locus_works() {
+        assert_eq!(
+            parse_locus("chr1:1000"),
+            Ok(Locus::Point {
+                path: "chr1".to_string(),
+                pos: 1000
+            })
+        );
+        assert_eq!(
+            parse_locus("chr1:1000-2000"),
+            Ok(Locus::Range(StringRegion::new("chr1:1000-2000").unwrap()))
+        );
+        assert_eq!(
+            parse_locus("chr1 1000 2000"),
+            Ok(Locus::Range(StringRegion::new("chr1:1000-2000").unwrap()))
+        );
+        assert!(parse_locus("chr1").is_err());
+        assert!(parse_locus("chr1:").is_err());
+        assert!(parse_locus("chr1:1000-").is_err());
+        assert!(parse_locus("").is_err());
+    }
+
+    #[test]
+    fn string_region_containment() {
+        let inverted = StringRegion::new("chr1:200-100").unwrap();
+        assert!(inverted.contains_pos("chr1", 100));
+        assert!(inverted.contains_pos("chr1", 199));
+        assert!(!inverted.contains_pos("chr1", 200));
+        assert!(!inverted.contains_pos("chr1", 99));
+        assert!(!inverted.contains_pos("1", 150));
+        assert!(inverted.contains_pos_by("1", 150, eq_ignore_chr_prefix));
+
+        assert!(inverted.contains_region(&StringRegion::new("chr1:150-200").unwrap()));
+        assert!(inverted.contains_region(&StringRegion::new("chr1:190-120").unwrap()));
+        assert!(inverted.contains_region(&inverted.clone()));
+        assert!(!inverted.contains_region(&StringRegion::new("chr1:150-201").unwrap()));
+        let unprefixed = StringRegion::new("1:150-160").unwrap();
+        assert!(!inverted.contains_region(&unprefixed));
+        assert!(inverted.contains_region_by(&unprefixed, eq_ignore_chr_prefix));
+        assert!(!eq_ignore_chr_prefix("chr1", "chr2"));
+    }
+
+    #[test]
+    fn region_expand_to_min_len() {
+        let mut a = Region::new(0, 1000, 1001);
+        a.expand_to_min_len(100);
+        assert_eq!(a, Region::new(0, 951, 1051));
+        let mut origin = Region::new(0, 0, 1);
+        origin.expand_to_min_len(100);
+        assert_eq!(origin, Region::new(0, 0, 100));
+        let mut near = Region::new(0, 10, 11);
+        near.expand_to_min_len(100);
+        assert_eq!(near, Region::new(0, 0, 100));
+        let mut wide = Region::new(0, 10, 500);
+        wide.expand_to_min_len(100);
+        assert_eq!(wide, Region::new(0, 10, 500));
+    }
+
+    #[test]
+    fn optional_region_overlaps() {
+        let opt = |path: &str, start: Option<u64>, end: Option<u64>| OptionalRegion {
+            path: path.to_string(),
+            start,
+            end,
+        };
+        let whole = opt("chr1", None, None);
+        let from = opt("chr1", Some(100), None);
+        let until = opt("chr1", None, Some(100));
+        let both = opt("chr1", Some(100), Some(200));
+        let inverted = opt("chr1", Some(200), Some(100));
+        let early = StringRegion::new("chr1:10-50").unwrap();
+        let late = StringRegion::new("chr1:150-160").unwrap();
+        let other = StringRegion::new("chr2:150-160").unwrap();
+
+        assert!(whole.overlaps(&early) && whole.overlaps(&late));
+        assert!(!from.overlaps(&early) && from.overlaps(&late));
+        assert!(until.overlaps(&early) && !until.overlaps(&late));
+        assert!(!both.overlaps(&early) && both.overlaps(&late));
+        assert!(inverted.overlaps(&late));
+        assert!(!until.overlaps(&StringRegion::new("chr1:100-120").unwrap()));
+        for region in &[&whole, &from, &until, &both] {
+            assert!(!region.overlaps(&other));
+        }
+
+        assert!(whole.overlaps_optional(&from));
+        assert!(whole.overlaps_optional(&until));
+        assert!(from.overlaps_optional(&opt("chr1", Some(5000), None)));
+        assert!(!from.overlaps_optional(&until));
+        assert!(until.overlaps_optional(&opt("chr1", None, Some(1))));
+        assert!(both.overlaps_optional(&from));
+        assert!(!both.overlaps_optional(&opt("chr1", Some(200), None)));
+        assert!(!whole.overlaps_optional(&opt("chr2", None, None)));
+
+        assert!(whole.contains_pos("chr1", 0));
+        assert!(from.contains_pos("chr1", 100) && !from.contains_pos("chr1", 99));
+        assert!(until.contains_pos("chr1", 99) && !until.contains_pos("chr1", 100));
+        assert!(both.contains_pos("chr1", 199) && !both.contains_pos("chr1", 200));
+        assert!(!whole.contains_pos("chr2", 0));
+    }
+
+    #[test]
+    fn parse_all_lenient_collects_errors() {
+        let lines = ["chr1:1-2", "bogus", "chr2 10 20", "chr3:x-5", "chr4:9-3"];
+        let (regions, errors) = StringRegion::parse_all_lenient(&lines);
+        let parsed: Vec<_> = regions.iter().map(|r| r.uuid()).collect();
+        assert_eq!(parsed, vec!["chr1:1-2", "chr2:10-20", "chr4:9-3"]);
+        let indices: Vec<_> = errors.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, vec![1, 3]);
+        assert!(matches!(errors[0].1, RegionParseError::InvalidFormat(_)));
+        assert_eq!(StringRegion::parse_all_lenient(&[]), (vec![], vec![]));
+    }
+
+    #[test]
+    fn group_by_reference_keeps_order() {
+        let regions = vec![
+            Region::new(2, 50, 60),
+            Region::new(0, 30, 40),
+            Region::new(2, 10, 20),
+            Region::new(0, 0, 5),
+        ];
+        assert!(regions[0].same_reference(&regions[2]));
+        assert!(!regions[0].same_reference(&regions[1]));
+        let groups = group_by_reference(regions);
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(
+            groups[&0],
+            vec![Region::new(0, 30, 40), Region::new(0, 0, 5)]
+        );
+        assert_eq!(
+            groups[&2],
+            vec![Region::new(2, 50, 60), Region::new(2, 10, 20)]
+        );
+        assert!(group_by_reference(vec![]).is_empty());
+    }
+
+    #[test]
+    fn region_jaccard() {
+        let a = Region::new(0, 0, 100);
+        let b = Region::new(0, 50, 150);
+        assert_eq!(a.overlap_len(&b), 50);
+        assert_eq!(a.jaccard(&b), 50.0 / 150.0);
+        assert_eq!(a.jaccard(&a.clone()), 1.0);
+        assert_eq!(a.jaccard(&Region::new(0, 100, 200)), 0.0);
+        assert_eq!(a.jaccard(&Region::new(1, 0, 100)), 0.0);
+        assert_eq!(Region::new(0, 5, 5).jaccard(&Region::new(0, 5, 5)), 0.0);
+    }
+
+    #[test]
+    fn region_jaccard_sets() {
+        let a = vec![
+            Region::new(1, 0, 10),
+            Region::new(0, 50, 100),
+            Region::new(0, 0, 20),
+            Region::new(0, 10, 30),
+        ];
+        let b = vec![Region::new(0, 20, 60), Region::new(1, 5, 15)];
+        // a merged: 0:[0,30) 0:[50,100) 1:[0,10) = 90; b = 50; shared: 10 + 10 + 5 = 25.
+        assert_eq!(jaccard_sets(&a, &b), 25.0 / (90.0 + 50.0 - 25.0));
+        assert_eq!(jaccard_sets(&a, &b), jaccard_sets(&b, &a));
+        assert_eq!(jaccard_sets(&a, &a), 1.0);
+        assert_eq!(jaccard_sets(&[], &[]), 0.0);
+        assert_eq!(jaccard_sets(&a, &[]), 0.0);
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(x.jaccard(y), y.jaccard(x));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn region_serde_coordinate_base() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Record {
+            native: Region,
+            #[serde(with = "one_based")]
+            external: Region,
+        }
+        let record = Record {
+            native: Region::new(1, 99, 200),
+            external: Region::new(1, 99, 200),
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            r#"{"native":{"ref_id":1,"start":99,"end":200},"external":{"ref_id":1,"start":100,"end":200}}"#
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+        let bad = r#"{"native":{"ref_id":1,"start":0,"end":1},"external":{"ref_id":1,"start":0,"end":1}}"#;
+        assert!(serde_json::from_str::<Record>(bad).is_err());
+    }
+
+    #[test]
+    fn region_split_evenly() {
+        let a = Region::new(0, 100, 200);
+        let bins = a.split_evenly(4);
+        assert_eq!(bins.len(), 4);
+        assert!(bins.iter().all(|b| b.len() == 25));
+        assert_eq!(bins[3], Region::new(0, 175, 200));
+        let bins = Region::new(0, 0, 10).split_evenly(3);
+        assert_eq!(
+            bins,
+            vec![
+                Region::new(0, 0, 4),
+                Region::new(0, 4, 7),
+                Region::new(0, 7, 10)
+            ]
+        );
+        let bins = Region::new(0, 0, 5).split_evenly(5);
+        assert_eq!(bins.len(), 5);
+        assert!(bins.iter().all(|b| b.len() == 1));
+        assert_eq!(Region::new(0, 0, 3).split_evenly(10).len(), 3);
+        assert!(a.split_evenly(0).is_empty());
+        assert!(Region::new(0, 5, 5).split_evenly(3).is_empty());
+    }
+
+    #[test]
+    fn string_region_split_evenly() {
+        let show = |v: Vec<StringRegion>| v.iter().map(|r| r.uuid()).collect::<Vec<_>>();
+        let forward = StringRegion::new("chr1:0-10").unwrap();
+        assert_eq!(
+            show(forward.split_evenly(3)),
+            vec!["chr1:0-4", "chr1:4-7", "chr1:7-10"]
+        );
+        let inverted = StringRegion::new("chr1:10-0").unwrap();
+        assert_eq!(
+            show(inverted.split_evenly(3)),
+            vec!["chr1:10-6", "chr1:6-3", "chr1:3-0"]
+        );
+        assert_eq!(inverted.split_evenly(20).len(), 10);
+    }
+
+    #[test]
+    fn region_checked_len() {
+        let region = Region::try_from((0, 10, 25)).unwrap();
+        assert_eq!(region.checked_len(), Some(15));
+        assert_eq!(region.checked_len(), Some(region.len()));
+        assert!(Region::try_from((0, 25, 10)).is_err());
+        let invalid = Region {
+            ref_id: 0,
+            start: 25,
+            end: 10,
+        };
+        assert_eq!(invalid.checked_len(), None);
+    }
+
+    #[test]
+    fn region_windows() {
+        let a = Region::new(0, 100, 200);
+        let tiles: Vec<_> = a.windows(25, 25).unwrap().collect();
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles[0], Region::new(0, 100, 125));
+        assert_eq!(tiles[3], Region::new(0, 175, 200));
+
+        let b = Region::new(0, 0, 10);
+        let overlapping = b.windows(4, 2).unwrap();
+        assert_eq!(overlapping.len(), 4);
+        assert_eq!(
+            overlapping
+                .map(|r| (r.start(), r.end()))
+                .collect::<Vec<_>>(),
+            vec![(0, 4), (2, 6), (4, 8), (6, 10)]
+        );
+        let partial = b.windows_partial(4, 2).unwrap();
+        assert_eq!(partial.len(), 5);
+        assert_eq!(partial.last(), Some(Region::new(0, 8, 10)));
+
+        let c = Region::new(0, 0, 11);
+        assert_eq!(c.windows(5, 5).unwrap().count(), 2);
+        assert_eq!(
+            c.windows_partial(5, 5).unwrap().collect::<Vec<_>>(),
+            vec![
+                Region::new(0, 0, 5),
+                Region::new(0, 5, 10),
+                Region::new(0, 10, 11)
+            ]
+        );
+        assert_eq!(Region::new(0, 0, 3).windows(5, 1).unwrap().count(), 0);
+        assert_eq!(
+            Region::new(0, 0, 3).windows_partial(5, 1).unwrap().count(),
+            3
+        );
+        assert!(a.windows(0, 5).is_err());
+        assert!(a.windows_partial(5, 0).is_err());
+    }
+
+    #[test]
+    fn string_region_windows() {
+        let a = StringRegion::new("chr1:0-11").unwrap();
+        let tiles: Vec<_> = a.windows(5, 5).unwrap().map(|r| r.uuid()).collect();
+        assert_eq!(tiles, vec!["chr1:0-5", "chr1:5-10"]);
+        let tiles: Vec<_> = a.windows_partial(5, 5).unwrap().map(|r| r.uuid()).collect();
+        assert_eq!(tiles, vec!["chr1:0-5", "chr1:5-10", "chr1:10-11"]);
+        assert!(a.windows(5, 0).is_err());
+    }
+
+    #[test]
+    fn string_region_shift_to_zero() {
+        let a = StringRegion::new("chr1:300-120").unwrap();
+        let local = a.shift_to_zero();
+        assert_eq!(local.start(), 0);
+        assert_eq!(local.interval(), a.interval());
+        assert!(local.inverted());
+        assert_eq!(local.uuid(), "chr1:180-0");
+    }
+
+    #[test]
+    fn string_region_tiles_aligned() {
+        let tiles = |s: &str| {
+            StringRegion::new(s)
+                .unwrap()
+                .tiles_aligned(100)
+                .unwrap()
+                .map(|r| r.uuid())
+                .collect::<Vec<_>>()
+        };
+        let mid = StringRegion::new("chr1:150-420").unwrap();
+        assert_eq!(mid.bin_index_range(100), (1, 5));
+        assert_eq!(
+            tiles("chr1:150-420"),
+            vec![
+                "chr1:150-200",
+                "chr1:200-300",
+                "chr1:300-400",
+                "chr1:400-420"
+            ]
+        );
+        let aligned = StringRegion::new("chr1:100-300").unwrap();
+        assert_eq!(aligned.bin_index_range(100), (1, 3));
+        assert_eq!(tiles("chr1:100-300"), vec!["chr1:100-200", "chr1:200-300"]);
+        let small = StringRegion::new("chr1:110-120").unwrap();
+        assert_eq!(small.bin_index_range(100), (1, 2));
+        assert_eq!(tiles("chr1:110-120"), vec!["chr1:110-120"]);
+        let empty = StringRegion::new("chr1:100-100").unwrap();
+        assert_eq!(empty.bin_index_range(100), (1, 1));
+        assert_eq!(tiles("chr1:100-100"), Vec::<String>::new());
+        let empty_inside = StringRegion::new("chr1:150-150").unwrap();
+        assert_eq!(empty_inside.bin_index_range(100), (1, 1));
+        assert_eq!(tiles("chr1:150-150"), Vec::<String>::new());
+        assert!(mid.tiles_aligned(0).is_err());
+    }
+
+    #[test]
+    fn region_classify() {
+        let a = Region::new(0, 10, 20);
+        assert_eq!(a.classify(&Region::new(0, 30, 40)), OverlapKind::Disjoint);
+        assert_eq!(a.classify(&Region::new(0, 20, 40)), OverlapKind::Abutting);
+        assert_eq!(a.classify(&Region::new(0, 0, 10)), OverlapKind::Abutting);
+        assert_eq!(
+            a.classify(&Region::new(0, 15, 40)),
+            OverlapKind::Overlapping
+        );
+        assert_eq!(a.classify(&Region::new(0, 12, 20)), OverlapKind::Contains);
+        assert_eq!(
+            a.classify(&Region::new(0, 10, 40)),
+            OverlapKind::ContainedBy
+        );
+        assert_eq!(a.classify(&a.clone()), OverlapKind::Equal);
+        assert_eq!(
+            a.classify(&Region::new(1, 10, 20)),
+            OverlapKind::DifferentRef
+        );
+    }
+
+    #[test]
+    fn string_region_as_borrowed() {
+        let region = StringRegion::new("chr1:200-100:-").unwrap();
+        assert_eq!(region.path(), "chr1");
+        let view = region.as_borrowed();
+        assert_eq!(view.path().as_ptr(), region.path().as_ptr());
+        assert!(view.inverted());
+        assert_eq!(view.strand(), Some(Strand::Reverse));
+        assert_eq!(view.into_owned(), region);
+    }
+
+    #[test]
+    fn chrom_sizes_parse() {
+        let sizes = ChromSizes::parse("# genome\nchr1\t1000\n\nchr2 500\n").unwrap();
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes.get("chr1"), Some(1000));
+        assert_eq!(sizes.get("chr3"), None);
+        assert_eq!(
+            sizes.require("chr3"),
+            Err(RegionParseError::UnknownContig("chr3".to_string()))
+        );
+        assert!(ChromSizes::parse("chr1").is_err());
+        assert!(ChromSizes::parse("chr1 x").is_err());
+        let collected: ChromSizes = vec![("chr1", 1000), ("chr2", 500)].into_iter().collect();
+        assert_eq!(collected, sizes);
+    }
+
+    #[test]
+    fn region_midpoint_and_resize() {
+        assert_eq!(Region::new(0, 10, 13).midpoint(), 11);
+        assert_eq!(Region::new(0, 10, 14).midpoint(), 12);
+        assert_eq!(
+            Region::new(0, 10, 13).resize_centered(1),
+            Region::new(0, 11, 12)
+        );
+        assert_eq!(
+            Region::new(0, 10, 13).resize_centered(5),
+            Region::new(0, 9, 14)
+        );
+        assert_eq!(
+            Region::new(0, 10, 14).resize_centered(2),
+            Region::new(0, 11, 13)
+        );
+        assert_eq!(
+            Region::new(0, 0, 4).resize_centered(10),
+            Region::new(0, 0, 7)
+        );
+    }
+
+    #[test]
+    fn string_region_midpoint_and_resize() {
+        let a = StringRegion::new("chr1:120-100").unwrap();
+        assert_eq!(a.midpoint(), 110);
+        let resized = a.resize_centered(1);
+        assert_eq!(resized.uuid(), "chr1:111-110");
+        assert_eq!(a.resize_centered(40).uuid(), "chr1:130-90");
+        let origin = StringRegion::new("chr1:0-10").unwrap();
+        assert_eq!(origin.resize_centered(100).uuid(), "chr1:0-55");
+        let sizes: ChromSizes = vec![("chr1", 50)].into_iter().collect();
+        assert_eq!(
+            origin.resize_centered_within(100, &sizes).unwrap().uuid(),
+            "chr1:0-50"
+        );
+        let unknown = StringRegion::new("chr2:0-10").unwrap();
+        assert!(unknown.resize_centered_within(100, &sizes).is_err());
+    }
+
+    #[test]
+    fn region_fit_within() {
+        let viewport = Region::new(0, 100, 200);
+        assert_eq!(
+            Region::new(0, 150, 300).fit_within(&viewport),
+            Some(Region::new(0, 150, 200))
+        );
+        assert_eq!(
+            Region::new(0, 120, 130).fit_within(&viewport),
+            Some(Region::new(0, 120, 130))
+        );
+        assert_eq!(
+            Region::new(0, 0, 1000).fit_within(&viewport),
+            Some(viewport.clone())
+        );
+        assert_eq!(Region::new(0, 200, 300).fit_within(&viewport), None);
+        assert_eq!(Region::new(1, 120, 130).fit_within(&viewport), None);
+    }
+
+    #[test]
+    fn region_shift() {
+        let a = Region::new(0, 100, 200);
+        assert_eq!(a.shift(0), a);
+        assert_eq!(a.shift(-50), Region::new(0, 50, 150));
+        assert_eq!(a.shift(-150), Region::new(0, 0, 50));
+        assert_eq!(a.shift(-500), Region::new(0, 0, 0));
+        assert!(a.try_shift(-150).is_err());
+        assert_eq!(a.try_shift(-100), Ok(Region::new(0, 0, 100)));
+        let high = Region::new(0, u64::MAX - 20, u64::MAX - 10);
+        assert_eq!(high.shift(15), Region::new(0, u64::MAX - 5, u64::MAX));
+        assert_eq!(high.shift(i64::MAX), Region::new(0, u64::MAX, u64::MAX));
+        assert!(high.try_shift(15).is_err());
+    }
+
+    #[test]
+    fn string_region_shift() {
+        let a = StringRegion::new("chr1:200-100").unwrap();
+        assert_eq!(a.shift(0), a);
+        assert_eq!(a.shift(-150).uuid(), "chr1:50-0");
+        assert_eq!(a.shift(50).uuid(), "chr1:250-150");
+        assert!(a.try_shift(-150).is_err());
+        assert_eq!(a.try_shift(-100).unwrap().uuid(), "chr1:100-0");
+        let sizes: ChromSizes = vec![("chr1", 220)].into_iter().collect();
+        assert_eq!(a.shift_within(50, &sizes).unwrap().uuid(), "chr1:220-150");
+        assert_eq!(a.shift_within(500, &sizes).unwrap().uuid(), "chr1:220-220");
+        assert!(StringRegion::new("chr2:1-2")
+            .unwrap()
+            .shift_within(1, &sizes)
+            .is_err());
+        let high = StringRegion::new_inner("chr1".to_string(), u64::MAX - 20, u64::MAX - 10);
+        assert_eq!(high.shift(15).end(), u64::MAX);
+        assert!(high.try_shift(15).is_err());
+    }
+
+    #[test]
+    fn string_region_from_pair_and_triple() {
+        let a = StringRegion::from_pair("chr1", (100, 200));
+        assert_eq!(a, StringRegion::new("chr1:100-200").unwrap());
+        let b = StringRegion::from_pair(String::from("chr1"), (200, 100));
+        assert!(b.inverted());
+        assert_eq!(b, StringRegion::new("chr1:200-100").unwrap());
+        assert_eq!(StringRegion::from_triple(("chr1", 200, 100)), b);
+        assert_eq!(StringRegion::from_triple(("chr1".to_string(), 100, 200)), a);
+    }
+
+    #[test]
+    fn string_region_flanks() {
+        let mut gene = StringRegion::new("chr1:1000-2000").unwrap();
+        assert_eq!(gene.flank_left(300).uuid(), "chr1:700-1000");
+        assert_eq!(gene.flank_right(300).uuid(), "chr1:2000-2300");
+
+        gene.set_strand(Some(Strand::Forward));
+        assert_eq!(gene.upstream(300).uuid(), "chr1:700-1000:+");
+        assert_eq!(gene.downstream(300).uuid(), "chr1:2000-2300:+");
+        gene.set_strand(Some(Strand::Reverse));
+        assert_eq!(gene.upstream(300).uuid(), "chr1:2000-2300:-");
+        assert_eq!(gene.downstream(300).uuid(), "chr1:700-1000:-");
+        gene.set_strand(Some(Strand::Unknown));
+        assert_eq!(gene.upstream(300).uuid(), "chr1:700-1000:.");
+        let inverted = StringRegion::new("chr1:2000-1000").unwrap();
+        assert_eq!(inverted.upstream(300).uuid(), "chr1:2300-2000");
+
+        let near_start = StringRegion::new("chr1:100-500:+").unwrap();
+        assert_eq!(near_start.upstream(300).uuid(), "chr1:0-100:+");
+        let sizes: ChromSizes = vec![("chr1", 600)].into_iter().collect();
+        let (flank, truncated) = near_start.upstream_within(300, &sizes).unwrap();
+        assert_eq!(flank.uuid(), "chr1:0-100:+");
+        assert!(truncated);
+        let (flank, truncated) = near_start.downstream_within(300, &sizes).unwrap();
+        assert_eq!(flank.uuid(), "chr1:500-600:+");
+        assert!(truncated);
+        let (flank, truncated) = near_start.downstream_within(50, &sizes).unwrap();
+        assert_eq!(flank.uuid(), "chr1:500-550:+");
+        assert!(!truncated);
+        assert!(StringRegion::new("chr9:1-2")
+            .unwrap()
+            .upstream_within(5, &sizes)
+            .is_err());
+    }
+
+    #[test]
+    fn region_midpoint_region() {
+        let summit = Region::new(3, 1000, 1001);
+        assert_eq!(summit.midpoint_region(10), Region::new(3, 995, 1005));
+        assert_eq!(summit.midpoint_region(11), Region::new(3, 995, 1006));
+        let peak = Region::new(3, 1000, 1100);
+        assert_eq!(peak.midpoint_region(20), Region::new(3, 1040, 1060));
+        assert_eq!(
+            Region::new(3, 2, 4).midpoint_region(20),
+            Region::new(3, 0, 13)
+        );
+    }
+
+    #[test]
+    fn region_pad_like_bedtools_slop() {
+        let a = Region::new(0, 5, 10);
+        assert_eq!(a.pad(5, 5), Region::new(0, 0, 15));
+        assert_eq!(a.pad(2, 3), Region::new(0, 3, 13));
+        assert_eq!(a.pad_within(2, 3000, 1000), Region::new(0, 3, 1000));
+        assert_eq!(a.pad(0, 0), a);
+    }
+
+    #[test]
+    fn string_region_pad_like_bedtools_slop() {
+        let plus = StringRegion::from_bed_fields(&["chr1", "5", "10", "a1", "1", "+"]).unwrap();
+        let minus = StringRegion::from_bed_fields(&["chr1", "5", "10", "a2", "2", "-"]).unwrap();
+        let sizes: ChromSizes = vec![("chr1", 1000)].into_iter().collect();
+        // bedtools slop -b 5
+        assert_eq!(plus.pad(5, 5).to_bed_fields()[1..3], ["0", "15"]);
+        // bedtools slop -l 2 -r 3
+        assert_eq!(plus.pad(2, 3).to_bed_fields()[1..3], ["3", "13"]);
+        assert_eq!(minus.pad(2, 3).to_bed_fields()[1..3], ["3", "13"]);
+        // bedtools slop -l 2 -r 3 -s
+        assert_eq!(plus.pad_stranded(2, 3).to_bed_fields()[1..3], ["3", "13"]);
+        assert_eq!(minus.pad_stranded(2, 3).to_bed_fields()[1..3], ["2", "12"]);
+        // bedtools slop -r 2000 against a 1 kb chromosome
+        let clipped = plus.pad_within(0, 2000, &sizes).unwrap();
+        assert_eq!(clipped.to_bed_fields()[1..3], ["5", "1000"]);
+        assert!(StringRegion::new("chr2:1-2")
+            .unwrap()
+            .pad_within(1, 1, &sizes)
+            .is_err());
+        let mut extended = StringRegion::new("chr1:5-10").unwrap();
+        extended.extend(5);
+        assert_eq!(extended, StringRegion::new("chr1:0-15").unwrap());
+    }
+
+    #[test]
+    fn region_iter_contained() {
+        let outer = Region::new(0, 100, 200);
+        let others = vec![
+            Region::new(0, 100, 200),
+            Region::new(0, 150, 200),
+            Region::new(0, 90, 120),
+            Region::new(0, 120, 130),
+            Region::new(1, 120, 130),
+            Region::new(0, 190, 210),
+        ];
+        let inside: Vec<_> = outer.iter_contained(&others).cloned().collect();
+        assert_eq!(
+            inside,
+            vec![
+                Region::new(0, 100, 200),
+                Region::new(0, 150, 200),
+                Region::new(0, 120, 130)
+            ]
+        );
+    }
+
+    #[test]
+    fn region_trim_to() {
+        let viewport = Region::new(0, 100, 200);
+        let mut features = vec![
+            Region::new(0, 50, 150),
+            Region::new(0, 150, 250),
+            Region::new(0, 50, 250),
+            Region::new(0, 120, 130),
+            Region::new(0, 200, 250),
+            Region::new(1, 120, 130),
+        ];
+        assert_eq!(features[4].trim_to(&viewport), None);
+        Region::trim_all(&mut features, &viewport);
+        assert_eq!(
+            features,
+            vec![
+                Region::new(0, 100, 150),
+                Region::new(0, 150, 200),
+                Region::new(0, 100, 200),
+                Region::new(0, 120, 130),
+            ]
+        );
+    }
+
+    #[test]
+    fn string_region_trim_to() {
+        let viewport = StringRegion::new("chr1:100-200").unwrap();
+        let mut features: Vec<_> = [
+            "chr1:150-50",
+            "chr1:150-250",
+            "chr1:250-50",
+            "chr1:120-130",
+            "chr1:0-100",
+            "chr2:120-130",
+        ]
+        .iter()
+        .map(|s| StringRegion::new(s).unwrap())
+        .collect();
+        assert_eq!(
+            features[0].trim_to(&viewport).unwrap().uuid(),
+            "chr1:150-100"
+        );
+        StringRegion::trim_all(&mut features, &viewport);
+        let trimmed: Vec<_> = features.iter().map(|r| r.uuid()).collect();
+        assert_eq!(
+            trimmed,
+            vec![
+                "chr1:150-100",
+                "chr1:150-200",
+                "chr1:200-100",
+                "chr1:120-130"
+            ]
+        );
+    }
+
+    #[test]
+    fn region_from_center() {
+        assert_eq!(Region::from_center(1, 500, 50), Region::new(1, 450, 550));
+        assert_eq!(Region::from_center(1, 0, 50), Region::new(1, 0, 50));
+        assert_eq!(Region::from_center(1, 20, 50), Region::new(1, 0, 70));
+        assert_eq!(Region::from_center(1, 20, 0), Region::new(1, 20, 20));
+    }
+
+    #[test]
+    fn region_split_at() {
+        let a = Region::new(0, 100, 200);
+        assert_eq!(
+            a.split_at(150),
+            Some((Region::new(0, 100, 150), Region::new(0, 150, 200)))
+        );
+        assert_eq!(a.split_at(100), None);
+        assert_eq!(a.split_at(200), None);
+        assert_eq!(a.split_at(300), None);
+        assert_eq!(
+            a.split_at_many(&[180, 100, 120, 180, 500, 120]),
+            vec![
+                Region::new(0, 100, 120),
+                Region::new(0, 120, 180),
+                Region::new(0, 180, 200)
+            ]
+        );
+        assert_eq!(a.split_at_many(&[]), vec![a.clone()]);
+        assert!(a.split_at_many_strict(&[150, 500]).is_err());
+        assert_eq!(a.split_at_many_strict(&[100, 150]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn string_region_split_at() {
+        let show = |v: Vec<StringRegion>| v.iter().map(|r| r.uuid()).collect::<Vec<_>>();
+        let forward = StringRegion::new("chr1:100-200").unwrap();
+        assert_eq!(
+            show(forward.split_at_many(&[150, 100, 120, 150])),
+            vec!["chr1:100-120", "chr1:120-150", "chr1:150-200"]
+        );
+        let inverted = StringRegion::new("chr1:200-100").unwrap();
+        assert_eq!(
+            show(inverted.split_at_many(&[150, 100, 120, 150])),
+            vec!["chr1:200-150", "chr1:150-120", "chr1:120-100"]
+        );
+        let (first, second) = inverted.split_at(150).unwrap();
+        assert_eq!(
+            (first.uuid(), second.uuid()),
+            ("chr1:200-150".to_string(), "chr1:150-100".to_string())
+        );
+        assert_eq!(inverted.split_at(100), None);
+        assert!(inverted.split_at_many_strict(&[99]).is_err());
+    }
+
+    #[test]
+    fn string_region_display_compact() {
+        let whole = StringRegion::new("chrM:0-16569").unwrap();
+        assert_eq!(whole.display_compact(Some(16569)), "chrM");
+        assert_eq!(whole.display_compact(Some(20000)), "chrM:0-16569");
+        assert_eq!(whole.display_compact(None), "chrM:0-16569");
+        let partial = StringRegion::new("chrM:10-16569").unwrap();
+        assert_eq!(partial.display_compact(Some(16569)), "chrM:10-16569");
+    }
+
+    #[test]
+    fn region_align_to() {
+        let aligned = Region::new(0, 100, 300);
+        assert_eq!(
+            aligned.align_to(100, AlignMode::Expand),
+            Ok(Some(aligned.clone()))
+        );
+        assert_eq!(
+            aligned.align_to(100, AlignMode::Shrink),
+            Ok(Some(aligned.clone()))
+        );
+        assert_eq!(
+            Region::new(0, 101, 299).align_to(100, AlignMode::Expand),
+            Ok(Some(Region::new(0, 100, 300)))
+        );
+        assert_eq!(
+            Region::new(0, 99, 301).align_to(100, AlignMode::Shrink),
+            Ok(Some(Region::new(0, 100, 300)))
+        );
+        let small = Region::new(0, 110, 150);
+        assert_eq!(small.align_to(100, AlignMode::Shrink), Ok(None));
+        assert_eq!(
+            small.align_to(100, AlignMode::Expand),
+            Ok(Some(Region::new(0, 100, 200)))
+        );
+        assert!(small.align_to(0, AlignMode::Expand).is_err());
+    }
+
+    #[test]
+    fn string_region_align_to() {
+        let region = StringRegion::from_triple(("chr1", 950, 1050));
+        assert_eq!(
+            region.align_to(100, AlignMode::Expand),
+            Ok(Some(StringRegion::from_triple(("chr1", 900, 1100))))
+        );
+        let sizes = ChromSizes::parse("chr1\t1020\n").unwrap();
+        assert_eq!(
+            region.align_to_within(100, AlignMode::Expand, &sizes),
+            Ok(Some(StringRegion::from_triple(("chr1", 900, 1020))))
+        );
+        assert_eq!(region.align_to(100, AlignMode::Shrink), Ok(None));
+        let inverted = StringRegion::from_triple(("chr1", 1050, 850));
+        assert_eq!(
+            inverted.align_to(100, AlignMode::Shrink),
+            Ok(Some(StringRegion::from_triple(("chr1", 1000, 900))))
+        );
+        assert!(region
+            .align_to_within(100, AlignMode::Expand, &ChromSizes::new())
+            .is_err());
+    }
+
+    #[test]
+    fn region_sort_key() {
+        let mut regions = vec![
+            Region::new(2, 5, 10),
+            Region::new(1, 100, 200),
+            Region::new(1, 100, 150),
+            Region::new(0, 1 << 40, 1 << 41),
+            Region::new(1, 0, 1),
+        ];
+        let mut expected = regions.clone();
+        expected.sort_by_key(|r| (r.ref_id(), r.start(), r.end()));
+        let mut by_key = regions.clone();
+        by_key.sort_by_key(Region::sort_key);
+        assert_eq!(by_key, expected);
+        sort_regions(&mut regions);
+        assert_eq!(regions, expected);
+
+        let huge = Region::new(1u64 << 33, 0, 1);
+        assert!(!huge.is_packable());
+        let mut mixed = vec![huge.clone(), Region::new(3, 0, 1)];
+        sort_regions(&mut mixed);
+        assert_eq!(mixed, vec![Region::new(3, 0, 1), huge]);
+    }
+
+    #[test]
+    fn string_region_envelope() {
+        let regions = [
+            StringRegion::from_triple(("chr1", 100, 200)),
+            StringRegion::from_triple(("chr2", 50, 60)),
+            StringRegion::from_triple(("chr1", 500, 300)),
+            StringRegion::from_triple(("chr1", 150, 160)),
+        ];
+        let env = envelope(regions.iter());
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["chr1"], StringRegion::from_triple(("chr1", 100, 500)));
+        assert_eq!(env["chr2"], StringRegion::from_triple(("chr2", 50, 60)));
+
+        let single = envelope(regions[2..3].iter());
+        assert_eq!(
+            single["chr1"],
+            StringRegion::from_triple(("chr1", 300, 500))
+        );
+        assert!(envelope(std::iter::empty()).is_empty());
+        assert_eq!(
+            regions[0].hull(&regions[2]),
+            Some(StringRegion::from_triple(("chr1", 100, 500)))
+        );
+        assert_eq!(regions[0].hull(&regions[1]), None);
+    }
+
+    #[test]
+    fn string_region_unit_suffix() {
+        assert_eq!(
+            StringRegion::new("chr1:1000-2000 bp").ok(),
+            Some(StringRegion::from_triple(("chr1", 1000, 2000)))
+        );
+        assert_eq!(
+            StringRegion::new("chr1 1000 2000 bp").ok(),
+            Some(StringRegion::from_triple(("chr1", 1000, 2000)))
+        );
+        assert_eq!(
+            StringRegion::new("chr1:1-2 Mb").ok(),
+            Some(StringRegion::from_triple(("chr1", 1_000_000, 2_000_000)))
+        );
+        assert_eq!(
+            StringRegion::new("chr2 5 3 kb").ok(),
+            Some(StringRegion::from_triple(("chr2", 5000, 3000)))
+        );
+        assert_eq!(
+            StringRegion::new("chr1 1000 2000").ok(),
+            Some(StringRegion::from_triple(("chr1", 1000, 2000)))
+        );
+        assert!(StringRegion::new("kb").is_err());
+        assert!(StringRegion::new("chr1 1000 kb").is_err());
+        assert!(StringRegion::new("chr1:1-99999999999999999 Mb").is_err());
+        let bed_named_kb = StringRegion::new("chr1\t100\t200\tkb").unwrap();
+        assert_eq!((bed_named_kb.start(), bed_named_kb.end()), (100, 200));
+        let bed_named_mb = StringRegion::new("chr1\t100\t200\tMb\t0\t+").unwrap();
+        assert_eq!((bed_named_mb.start(), bed_named_mb.end()), (100, 200));
+        assert!(StringRegion::new("chr1:1-2 kb Mb").is_err());
+        let extra_columns = StringRegion::new("chr1 1 2 kb Mb").unwrap();
+        assert_eq!((extra_columns.start(), extra_columns.end()), (1, 2));
+    }
+
+    #[test]
+    fn region_adjacency() {
+        let a = Region::new(0, 10, 20);
+        let abutting = Region::new(0, 20, 30);
+        let gap_of_one = Region::new(0, 21, 30);
+        let overlapping = Region::new(0, 15, 30);
+        assert!(a.is_adjacent(&abutting) && abutting.is_adjacent(&a));
+        assert!(a.touches(&abutting) && !a.overlaps(&abutting));
+        assert!(!a.is_adjacent(&gap_of_one) && !a.touches(&gap_of_one));
+        assert!(!a.is_adjacent(&overlapping) && a.touches(&overlapping));
+        assert!(!a.touches(&Region::new(1, 20, 30)));
+        for other in [&abutting, &gap_of_one, &overlapping] {
+            assert_eq!(a.touches(other), a.union(other).is_some());
+        }
+
+        let s = StringRegion::from_triple(("chr1", 10, 20));
+        let s_abutting = StringRegion::from_triple(("chr1", 30, 20));
+        assert!(s.is_adjacent(&s_abutting) && s.touches(&s_abutting));
+        assert!(!s.is_adjacent(&StringRegion::from_triple(("chr1", 21, 30))));
+        assert!(!s.touches(&StringRegion::from_triple(("chr1", 21, 30))));
+        assert!(s.touches(&StringRegion::from_triple(("chr1", 15, 30))));
+        assert!(!s.touches(&StringRegion::from_triple(("chr2", 20, 30))));
+    }
+
+    #[test]
+    fn region_coverage() {
+        let window = Region::new(0, 0, 100);
+        let intervals = [
+            Region::new(0, 10, 50),
+            Region::new(0, 30, 70),
+            Region::new(1, 0, 100),
+        ];
+        assert_eq!(
+            window.coverage(&intervals),
+            vec![
+                (0, 10, 0),
+                (10, 30, 1),
+                (30, 50, 2),
+                (50, 70, 1),
+                (70, 100, 0)
+            ]
+        );
+        assert_eq!(
+            Region::new(0, 20, 40).coverage(&intervals),
+            vec![(20, 30, 1), (30, 40, 2)]
+        );
+        assert_eq!(
+            window.coverage(&[Region::new(0, 0, 50), Region::new(0, 50, 100)]),
+            vec![(0, 100, 1)]
+        );
+        assert_eq!(window.coverage(&[]), vec![(0, 100, 0)]);
+    }
+
+    #[test]
+    fn string_region_relative_position() {
+        let mut gene = StringRegion::from_triple(("chr1", 1000, 2000));
+        let before = StringRegion::from_triple(("chr1", 900, 950));
+        let after = StringRegion::from_triple(("chr1", 2000, 2010));
+        let inside = StringRegion::from_triple(("chr1", 1500, 1501));
+
+        gene.set_strand(Some(Strand::Forward));
+        assert_eq!(
+            before.relative_position(&gene),
+            Some(RelativePosition::Upstream(50))
+        );
+        assert_eq!(
+            after.relative_position(&gene),
+            Some(RelativePosition::Downstream(0))
+        );
+        assert_eq!(before.is_upstream_of(&gene), Some(true));
+        assert_eq!(after.is_downstream_of(&gene), Some(true));
+        assert_eq!(inside.is_upstream_of(&gene), Some(false));
+        assert_eq!(inside.is_downstream_of(&gene), Some(false));
+        assert_eq!(
+            inside.relative_position(&gene),
+            Some(RelativePosition::Overlapping)
+        );
+
+        gene.set_strand(Some(Strand::Reverse));
+        assert_eq!(
+            before.relative_position(&gene),
+            Some(RelativePosition::Downstream(50))
+        );
+        assert_eq!(after.is_upstream_of(&gene), Some(true));
+        assert_eq!(before.is_upstream_of(&gene), Some(false));
+
+        gene.set_strand(Some(Strand::Unknown));
+        assert_eq!(before.is_upstream_of(&gene), None);
+        gene.set_strand(None);
+        assert_eq!(before.relative_position(&gene), None);
+        gene.set_strand(Some(Strand::Forward));
+        let other_chrom = StringRegion::from_triple(("chr2", 900, 950));
+        assert_eq!(other_chrom.is_upstream_of(&gene), None);
+    }
+
+    #[test]
+    fn string_region_validate() {
+        let region = StringRegion::from_triple(("chr1", 100, 200));
+        assert!(region.validate(None).is_empty());
+        assert!(region.validate(Some(200)).is_empty());
+        assert_eq!(
+            region.validate(Some(150)),
+            vec![RegionWarning::ExceedsContig {
+                end: 200,
+                contig_len: 150
+            }]
+        );
+        assert_eq!(
+            StringRegion::from_triple(("chr1", 100, 100)).validate(None),
+            vec![RegionWarning::ZeroLength]
+        );
+        assert_eq!(
+            StringRegion::from_triple(("chr1", 300, 100)).validate(Some(250)),
+            vec![
+                RegionWarning::ExceedsContig {
+                    end: 300,
+                    contig_len: 250
+                },
+                RegionWarning::Inverted
+            ]
+        );
+    }
+
+    #[test]
+    fn string_region_offsets() {
+        let forward = StringRegion::from_triple(("chr1", 100, 110));
+        let inverted = StringRegion::from_triple(("chr1", 110, 100));
+        let mut reverse = forward.clone();
+        reverse.set_strand(Some(Strand::Reverse));
+        assert_eq!(forward.offset_of(100), Some(0));
+        assert_eq!(forward.offset_of(109), Some(9));
+        assert_eq!(inverted.offset_of(109), Some(0));
+        assert_eq!(reverse.offset_of(100), Some(9));
+        for region in [&forward, &inverted, &reverse] {
+            for pos in 90..120 {
+                match region.offset_of(pos) {
+                    Some(offset) => {
+                        assert!(region.contains_pos("chr1", pos));
+                        assert_eq!(region.position_at_offset(offset), Some(pos));
+                    }
+                    None => assert!(!region.contains_pos("chr1", pos)),
+                }
+            }
+            for offset in 0..20 {
+                match region.position_at_offset(offset) {
+                    Some(pos) => assert_eq!(region.offset_of(pos), Some(offset)),
+                    None => assert!(offset >= 10),
+                }
+            }
+        }
+
+        let sub = StringRegion::from_triple(("chr1", 102, 105));
+        assert_eq!(forward.project(&sub), Some(2..5));
+        assert_eq!(inverted.project(&sub), Some(5..8));
+        assert_eq!(reverse.project(&sub), Some(5..8));
+        assert_eq!(
+            forward.project(&StringRegion::from_triple(("chr1", 95, 105))),
+            None
+        );
+        assert_eq!(
+            forward.project(&StringRegion::from_triple(("chr2", 102, 105))),
+            None
+        );
+    }
+
+    #[test]
+    fn region_operators() {
+        let region = Region::new(0, 150, 200);
+        assert_eq!(region.clone() + 100, Region::new(0, 50, 300));
+        assert_eq!(region.clone() + 200, Region::new(0, 0, 400));
+        assert_eq!(region.clone() - 10, Region::new(0, 160, 190));
+        assert_eq!(region.clone() - 25, Region::new(0, 175, 175));
+        assert_eq!(region.clone() - 1000, Region::new(0, 175, 175));
+        assert_eq!(region.clone() << 100, Region::new(0, 50, 100));
+        assert_eq!(region.clone() << 180, Region::new(0, 0, 20));
+        assert_eq!(region.clone() >> 100, Region::new(0, 250, 300));
+        assert_eq!((region.clone() + 100) - 100, region);
+    }
+
+    #[test]
+    fn string_region_rebase_onto() {
+        let parent = StringRegion::from_triple(("chr1", 10000, 20000));
+        let hit = StringRegion::from_triple(("local", 500, 700));
+        let lifted = hit.rebase_onto(&parent).unwrap();
+        assert_eq!(lifted, StringRegion::from_triple(("chr1", 10500, 10700)));
+        let local = lifted.localize_within(&parent).unwrap();
+        assert_eq!(local.path(), "chr1:10000-20000");
+        assert_eq!((local.start(), local.end()), (500, 700));
+        assert_eq!(local.rebase_onto(&parent), Some(lifted));
+
+        let inverted_parent = StringRegion::from_triple(("chr1", 20000, 10000));
+        let lifted = hit.rebase_onto(&inverted_parent).unwrap();
+        assert_eq!(lifted, StringRegion::from_triple(("chr1", 19500, 19300)));
+        let local = lifted.localize_within(&inverted_parent).unwrap();
+        assert_eq!(
+            (local.start(), local.end(), local.inverted()),
+            (500, 700, false)
+        );
+        assert_eq!(local.rebase_onto(&inverted_parent), Some(lifted));
+
+        let past_end = StringRegion::from_triple(("local", 9900, 10001));
+        assert_eq!(past_end.rebase_onto(&parent), None);
+        let outside = StringRegion::from_triple(("chr1", 9000, 10500));
+        assert_eq!(outside.localize_within(&parent), None);
+    }
+
+    #[test]
+    fn string_region_parse_with_aliases() {
+        let resolve = |name: &str| match name {
+            "MT" | "chrM" | "NC_012920.1" => Some("chrM".to_string()),
+            "1" | "chr1" => Some("chr1".to_string()),
+            _ => None,
+        };
+        for alias in ["MT", "chrM", "NC_012920.1"] {
+            assert_eq!(
+                StringRegion::parse_with_aliases(&format!("{}:100-200", alias), resolve),
+                Ok(StringRegion::from_triple(("chrM", 100, 200)))
+            );
+        }
+        assert_eq!(
+            StringRegion::parse_with_aliases("chrUn:100-200", resolve),
+            Err(RegionParseError::UnknownContig("chrUn".to_string()))
+        );
+        assert!(matches!(
+            StringRegion::parse_with_aliases("MT", resolve),
+            Err(RegionParseError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn region_reverse_coordinates() {
+        let region = Region::new(3, 10, 30);
+        assert_eq!(region.reverse_coordinates(100), Ok(Region::new(3, 70, 90)));
+        assert!(region.reverse_coordinates(20).is_err());
+        for start in 0..20 {
+            for end in start..20 {
+                let region = Region::new(0, start, end);
+                let mirrored = region.reverse_coordinates(20).unwrap();
+                assert_eq!(mirrored.len(), region.len());
+                assert_eq!(mirrored.reverse_coordinates(20), Ok(region));
+            }
+        }
+
+        let mut stranded = StringRegion::from_triple(("chr1", 30, 10));
+        stranded.set_strand(Some(Strand::Forward));
+        let mirrored = stranded.reverse_coordinates(100).unwrap();
+        assert_eq!((mirrored.start(), mirrored.end()), (70, 90));
+        assert_eq!(mirrored.strand(), Some(Strand::Reverse));
+        assert!(mirrored.inverted());
+        assert_eq!(mirrored.reverse_coordinates(100), Ok(stranded.clone()));
+        assert_eq!(
+            stranded.reverse_coordinates_within(&ChromSizes::parse("chr1\t100\n").unwrap()),
+            Ok(mirrored)
+        );
+        assert!(stranded
+            .reverse_coordinates_within(&ChromSizes::new())
+            .is_err());
+        assert!(stranded.reverse_coordinates(29).is_err());
+    }
+
+    #[test]
+    fn region_split_on_bins() {
+        assert_eq!(
+            Region::new(0, 150, 420).split_on_bins(100),
+            Ok(vec![
+                Region::new(0, 150, 200),
+                Region::new(0, 200, 300),
+                Region::new(0, 300, 400),
+                Region::new(0, 400, 420),
+            ])
+        );
+        assert_eq!(
+            Region::new(0, 100, 300).split_on_bins(100),
+            Ok(vec![Region::new(0, 100, 200), Region::new(0, 200, 300)])
+        );
+        assert_eq!(
+            Region::new(0, 110, 150).split_on_bins(100),
+            Ok(vec![Region::new(0, 110, 150)])
+        );
+        assert_eq!(Region::new(0, 5, 5).split_on_bins(100), Ok(vec![]));
+        assert!(Region::new(0, 150, 420).split_on_bins(0).is_err());
+    }
+
+    #[test]
+    fn string_region_fractions() {
+        let forward = StringRegion::from_triple(("chr1", 100, 200));
+        let mut reverse = forward.clone();
+        reverse.set_strand(Some(Strand::Reverse));
+        assert_eq!(forward.position_at_fraction(0.0), Some(100));
+        assert_eq!(forward.position_at_fraction(0.5), Some(150));
+        assert_eq!(forward.position_at_fraction(1.0), Some(199));
+        assert_eq!(reverse.position_at_fraction(0.0), Some(199));
+        assert_eq!(reverse.position_at_fraction(0.5), Some(149));
+        assert_eq!(reverse.position_at_fraction(1.0), Some(100));
+        assert_eq!(forward.position_at_fraction(f64::NAN), None);
+        assert_eq!(forward.position_at_fraction(1.5), None);
+        assert_eq!(forward.position_at_fraction(-0.1), None);
+        assert_eq!(
+            StringRegion::from_triple(("chr1", 5, 5)).position_at_fraction(0.5),
+            None
+        );
+
+        let region = StringRegion::from_triple(("chr1", 0, 10));
+        assert_eq!(region.quantile_breakpoints(4), vec![3, 6, 8]);
+        let inverted = StringRegion::from_triple(("chr1", 10, 0));
+        assert_eq!(inverted.quantile_breakpoints(4), vec![7, 4, 2]);
+        assert_eq!(region.quantile_breakpoints(1), Vec::<u64>::new());
+        assert_eq!(region.quantile_breakpoints(0), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn string_region_reverse() {
+        let mut region = StringRegion::new("chr1:100-200").unwrap();
+        let reversed = region.reversed();
+        assert_eq!(reversed.to_string(), "chr1:200-100");
+        assert_eq!((reversed.start(), reversed.end()), (100, 200));
+        region.reverse();
+        assert_eq!(region, reversed);
+        region.reverse();
+        assert_eq!(region.to_string(), "chr1:100-200");
+    }
+
+    #[test]
+    fn string_region_signed_interval() {
+        let region = StringRegion::from_triple(("chr1", 100, 250));
+        assert_eq!(region.signed_interval(), Some(150));
+        assert_eq!(region.reversed().signed_interval(), Some(-150));
+        assert_eq!(region.reversed().abs_interval(), 150);
+        assert_eq!(region.reversed().interval(), 150);
+        let inverted = OptionalRegion::new("chr1:250-100").unwrap();
+        assert_eq!(inverted.signed_interval(), Some(-150));
+        assert_eq!(inverted.abs_interval(), Some(150));
+        assert_eq!(
+            OptionalRegion::new("chr1:100").unwrap().abs_interval(),
+            None
+        );
+
+        let max = StringRegion::from_triple(("chr1", 0, i64::MAX as u64));
+        assert_eq!(max.signed_interval(), Some(i64::MAX));
+        assert_eq!(max.reversed().signed_interval(), Some(-i64::MAX));
+        let past = StringRegion::from_triple(("chr1", 0, i64::MAX as u64 + 1));
+        assert_eq!(past.signed_interval(), None);
+        assert_eq!(past.reversed().signed_interval(), None);
+        assert_eq!(
+            StringRegion::from_triple(("chr1", 0, u64::MAX)).signed_interval(),
+            None
+        );
+    }
+
+    #[test]
+    fn region_try_union() {
+        let a = Region::new(0, 10, 20);
+        assert_eq!(
+            a.try_union(&Region::new(0, 20, 30)),
+            Ok(Region::new(0, 10, 30))
+        );
+        assert_eq!(
+            a.try_union(&Region::new(1, 15, 30)),
+            Err(RegionError::DifferentReference)
+        );
+        assert_eq!(
+            a.try_union(&Region::new(0, 25, 30)),
+            Err(RegionError::Disjoint)
+        );
+        assert_eq!(
+            RegionError::DifferentReference.to_string(),
+            "Regions are on different references"
+        );
+    }
+
+    #[test]
+    fn region_containment_family() {
+        let a = Region::new(0, 10, 20);
+        assert!(a.include(&a));
+        assert!(a.contains_region(&a) && a.is_contained_in(&a));
+        let sharing_end = Region::new(0, 15, 20);
+        assert!(a.include(&sharing_end));
+        assert!(sharing_end.is_contained_in(&a) && !a.is_contained_in(&sharing_end));
+        assert!(a.overlaps(&sharing_end));
+        assert!(a.contains(0, 19) && !a.contains(0, 20) && a.contains(0, 10));
+        let past_end = Region::new(0, 15, 21);
+        assert!(!a.contains_region(&past_end) && a.overlaps(&past_end));
+        let before_start = Region::new(0, 9, 20);
+        assert!(!a.contains_region(&before_start));
+        assert!(!a.contains_region(&Region::new(1, 12, 18)));
+        assert!(!a.contains(1, 15));
+        for pos in sharing_end.start()..sharing_end.end() {
+            assert!(a.contains(0, pos));
+        }
+    }
+
+    #[test]
+    fn bedpe_line() {
+        let to_id = |name: &str| match name {
+            "chr1" => Some(0),
+            "chr2" => Some(1),
+            _ => None,
+        };
+        assert_eq!(
+            parse_bedpe_line("chr1\t100\t200\tchr1\t5000\t5100\tpair1\t60\t+\t-", to_id),
+            Ok((Region::new(0, 100, 200), Region::new(0, 5000, 5100)))
+        );
+        assert_eq!(
+            parse_bedpe_line("chr1\t100\t200\tchr2\t300\t400", to_id),
+            Ok((Region::new(0, 100, 200), Region::new(1, 300, 400)))
+        );
+        assert_eq!(
+            parse_bedpe_line("chr1\t100\t200\tchrX\t300\t400", to_id),
+            Err(RegionParseError::UnknownContig("chrX".to_string()))
+        );
+        assert!(matches!(
+            parse_bedpe_line("chr1\t100\t200\tchr2\t300", to_id),
+            Err(RegionParseError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_bedpe_line("chr1\t100\t200\tchr2\t-1\t-1", to_id),
+            Err(RegionParseError::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn region_closedness() {
+        let region = Region::new(0, 100, 200);
+        assert!(!region.contains_with(0, 200, Closedness::HalfOpen));
+        assert!(region.contains_with(0, 200, Closedness::Closed));
+        assert!(!region.contains_with(0, 201, Closedness::Closed));
+        assert!(region.contains_with(0, 100, Closedness::Closed));
+        assert_eq!(
+            region.contains_with(0, 199, Closedness::HalfOpen),
+            region.contains(0, 199)
+        );
+        let next = Region::new(0, 200, 300);
+        assert!(!region.overlaps_with(&next, Closedness::HalfOpen));
+        assert!(region.overlaps_with(&next, Closedness::Closed));
+        assert!(!region.overlaps_with(&Region::new(0, 201, 300), Closedness::Closed));
+        assert!(!region.overlaps_with(&Region::new(1, 200, 300), Closedness::Closed));
+
+        let s = StringRegion::from_triple(("chr1", 100, 200));
+        assert!(!s.contains_pos_with("chr1", 200, Closedness::HalfOpen));
+        assert!(s.contains_pos_with("chr1", 200, Closedness::Closed));
+        assert!(!s.contains_pos_with("chr2", 150, Closedness::Closed));
+        let s_next = StringRegion::from_triple(("chr1", 300, 200));
+        assert!(!s.overlaps_with(&s_next, Closedness::HalfOpen));
+        assert!(s.overlaps_with(&s_next, Closedness::Closed));
+    }
+
+    #[test]
+    fn region_adjacent() {
+        let tile = Region::new(2, 1000, 2000);
+        assert!(tile.adjacent(&Region::new(2, 2000, 3000)));
+        assert!(tile.adjacent(&Region::new(2, 0, 1000)));
+        assert!(!tile.adjacent(&Region::new(2, 1999, 3000)));
+        assert!(!tile.adjacent(&Region::new(2, 2001, 3000)));
+        assert!(!tile.adjacent(&Region::new(3, 2000, 3000)));
+    }
+
+    #[test]
+    fn string_region_coordinate_systems() {
+        let bed = StringRegion::from_triple(("chr1", 0, 1));
+        let closed = bed.to_one_closed(CoordinateSystem::ZeroHalfOpen).unwrap();
+        assert_eq!((closed.start(), closed.end()), (1, 1));
+        assert_eq!(
+            closed.to_zero_half_open(CoordinateSystem::OneClosed),
+            Ok(bed.clone())
+        );
+        assert_eq!(
+            bed.to_zero_half_open(CoordinateSystem::ZeroHalfOpen),
+            Ok(bed.clone())
+        );
+        assert_eq!(
+            closed.to_one_closed(CoordinateSystem::OneClosed),
+            Ok(closed.clone())
+        );
+
+        let from_one = StringRegion::from_triple(("chr1", 1, 10));
+        let zero = from_one
+            .to_zero_half_open(CoordinateSystem::OneClosed)
+            .unwrap();
+        assert_eq!((zero.start(), zero.end()), (0, 10));
+        assert_eq!(
+            zero.to_one_closed(CoordinateSystem::ZeroHalfOpen),
+            Ok(from_one)
+        );
+
+        assert!(StringRegion::from_triple(("chr1", 0, 10))
+            .to_zero_half_open(CoordinateSystem::OneClosed)
+            .is_err());
+        assert!(StringRegion::from_triple(("chr1", 5, 5))
+            .to_one_closed(CoordinateSystem::ZeroHalfOpen)
+            .is_err());
+        assert_eq!(
+            StringRegion::parse_as("chr1:1-10", CoordinateSystem::OneClosed),
+            Ok(StringRegion::from_triple(("chr1", 0, 10)))
+        );
+        assert!(StringRegion::parse_as("chr1:0-10", CoordinateSystem::OneClosed).is_err());
+    }
+
+    #[test]
+    fn string_region_records_its_coordinate_system() {
+        assert_eq!(StringRegion::new("chr1:1-10").unwrap().system(), None);
+        let opts = ParseOptions {
+            system: Some(CoordinateSystem::OneClosed),
+        };
+        let closed = StringRegion::parse_with("chr1:1-10", &opts).unwrap();
+        assert_eq!(closed.system(), Some(CoordinateSystem::OneClosed));
+        assert_eq!((closed.start(), closed.end()), (1, 10));
+        let unmarked = StringRegion::parse_with("chr1:1-10", &ParseOptions::default()).unwrap();
+        assert_eq!(unmarked.system(), None);
+        assert_eq!(closed, unmarked);
+
+        let zero = closed
+            .to_zero_half_open(CoordinateSystem::OneClosed)
+            .unwrap();
+        assert_eq!(zero.system(), Some(CoordinateSystem::ZeroHalfOpen));
+        let back = zero.to_one_closed(CoordinateSystem::ZeroHalfOpen).unwrap();
+        assert_eq!(back.system(), Some(CoordinateSystem::OneClosed));
+        assert_eq!(
+            StringRegion::parse_as("chr1:1-10", CoordinateSystem::OneClosed)
+                .unwrap()
+                .system(),
+            Some(CoordinateSystem::ZeroHalfOpen)
+        );
+        let bed = StringRegion::from_bed_fields(&["chr1", "0", "10"]).unwrap();
+        assert_eq!(bed.system(), Some(CoordinateSystem::ZeroHalfOpen));
+        let gff = ["chr1", ".", "gene", "1", "10", ".", "+"];
+        let gff = StringRegion::from_gff_fields(&gff).unwrap();
+        assert_eq!(gff.system(), Some(CoordinateSystem::ZeroHalfOpen));
+    }
+
+    #[test]
+    fn region_merge_iter() {
+        let mut regions = vec![
+            Region::new(0, 0, 10),
+            Region::new(0, 5, 20),
+            Region::new(0, 20, 25),
+            Region::new(0, 28, 30),
+            Region::new(0, 40, 50),
+            Region::new(1, 0, 10),
+            Region::new(1, 12, 15),
+            Region::new(2, 7, 9),
+        ];
+        for max_gap in [0, 3, 100] {
+            let streamed: Vec<Region> = MergeIter::new(regions.iter().cloned(), max_gap).collect();
+            let mut batch = regions.clone();
+            merge_overlaps_within(&mut batch, max_gap);
+            assert_eq!(streamed, batch, "max_gap = {}", max_gap);
+        }
+        regions.clear();
+        assert_eq!(MergeIter::new(regions.into_iter(), 0).next(), None);
+    }
+
+    #[test]
+    fn region_parse_and_convert_orientation() {
+        let to_id = |name: &str| if name == "chr1" { Some(0) } else { None };
+        let parsed = Region::parse("chr1:200-100", to_id).unwrap();
+        assert_eq!(parsed, Region::new(0, 100, 200));
+        assert_eq!(
+            Region::parse_oriented("chr1:200-100", to_id).unwrap(),
+            OrientedRegion {
+                region: Region::new(0, 100, 200),
+                reverse: true
+            }
+        );
+        assert!(
+            !Region::parse_oriented("chr1:100-200", to_id)
+                .unwrap()
+                .reverse
+        );
+        assert!(Region::parse("chr2:100-200", to_id).is_err());
+
+        let inverted = StringRegion::new("chr1:200-100").unwrap();
+        assert_eq!(
+            Region::convert(&inverted, to_id).unwrap(),
+            Region::new(0, 100, 200)
+        );
+        let oriented = Region::convert_oriented(&inverted, to_id).unwrap();
+        assert!(oriented.reverse);
+        assert_eq!(oriented.region, Region::new(0, 100, 200));
+        let inconsistent = StringRegion {
+            path: "chr1".to_string(),
+            start: 200,
+            end: 100,
+            inverted: false,
+            strand: None,
+            name: None,
+            score: None,
+            system: None,
+        };
+        assert!(Region::convert(&inconsistent, to_id).is_err());
+    }
+
+    #[test]
+    fn optional_region_contains_pos_parsed() {
+        let from = OptionalRegion::new("chr1:100-").unwrap();
+        assert!(from.contains_pos("chr1", 100) && from.contains_pos("chr1", u64::MAX - 1));
+        assert!(!from.contains_pos("chr1", 99));
+        let until = OptionalRegion::new("chr1:-200").unwrap();
+        assert!(until.contains_pos("chr1", 0) && until.contains_pos("chr1", 199));
+        assert!(!until.contains_pos("chr1", 200));
+        let both = OptionalRegion::new("chr1:100-200").unwrap();
+        assert!(both.contains_pos("chr1", 100) && !both.contains_pos("chr1", 200));
+        let bare = OptionalRegion::new("chr1:").unwrap();
+        assert_eq!((bare.start, bare.end), (None, None));
+        assert!(bare.contains_pos("chr1", 0) && bare.contains_pos("chr1", u64::MAX - 1));
+        assert!(!bare.contains_pos("chr2", 0));
+    }
+
+    #[test]
+    fn region_set() {
+        let mut set: RegionSet = vec![
+            StringRegion::from_triple(("chr2", 10, 20)),
+            StringRegion::from_triple(("chr1", 300, 400)),
+            StringRegion::from_triple(("chr1", 100, 200)),
+            StringRegion::from_triple(("chr1", 150, 250)),
+            StringRegion::from_triple(("chr1", 120, 130)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.total_len(), 320);
+        let query = StringRegion::from_triple(("chr1", 125, 160));
+        let hits: Vec<&StringRegion> = set.overlapping(&query).collect();
+        assert_eq!(
+            hits,
+            vec![
+                &StringRegion::from_triple(("chr1", 100, 200)),
+                &StringRegion::from_triple(("chr1", 120, 130)),
+                &StringRegion::from_triple(("chr1", 150, 250)),
+            ]
+        );
+        assert!(set.contains_pos("chr1", 399) && !set.contains_pos("chr1", 400));
+        assert!(!set.contains_pos("chr3", 0));
+
+        set.merge_overlaps(0);
+        let hits: Vec<&StringRegion> = set.overlapping(&query).collect();
+        assert_eq!(hits, vec![&StringRegion::from_triple(("chr1", 100, 250))]);
+        assert_eq!(set.total_len(), 260);
+        set.merge_overlaps(50);
+        set.extend(vec![StringRegion::from_triple(("chr1", 0, 10))]);
+        assert_eq!(
+            Vec::from(set),
+            vec![
+                StringRegion::from_triple(("chr1", 0, 10)),
+                StringRegion::from_triple(("chr1", 100, 400)),
+                StringRegion::from_triple(("chr2", 10, 20)),
+            ]
+        );
+
+        let mut empty = RegionSet::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.total_len(), 0);
+        assert_eq!(empty.overlapping(&query).count(), 0);
+        assert!(!empty.contains_pos("chr1", 0));
+        empty.merge_overlaps(10);
+        assert_eq!(empty.iter().count(), 0);
+        assert!(empty.into_vec().is_empty());
+    }
+
+    #[test]
+    fn region_bucket() {
+        let buckets: Vec<u64> = Region::new(0, 150, 320).bucket(100).collect();
+        assert_eq!(buckets, vec![1, 2, 3]);
+        let buckets: Vec<u64> = Region::new(0, 100, 300).bucket(100).collect();
+        assert_eq!(buckets, vec![1, 2]);
+        assert_eq!(Region::new(0, 150, 150).bucket(100).count(), 0);
+        assert_eq!(Region::new(0, 150, 320).bucket(0).count(), 0);
+        let mut tally = BTreeMap::new();
+        for region in [Region::new(0, 0, 150), Region::new(0, 120, 130)] {
+            for k in region.bucket(100) {
+                *tally.entry(k).or_insert(0) += 1;
+            }
+        }
+        assert_eq!(tally.into_iter().collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
     }
 
-    pub fn set_ref_id(&mut self, ref_id: u64) {
-        self.ref_id = ref_id;
+    #[test]
+    fn region_index_matches_brute_force() {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % bound
+        };
+        for n in [0, 1, 2, 3, 7, 16, 17, 100, 1000] {
+            let entries: Vec<(StringRegion, usize)> = (0..n)
+                .map(|i| {
+                    let path = if next(4) == 0 { "chr2" } else { "chr1" };
+                    let start = next(10_000);
+                    let len = if next(10) == 0 { next(3000) } else { next(100) };
+                    (StringRegion::from_triple((path, start, start + len)), i)
+                })
+                .collect();
+            let index: RegionIndex<usize> = entries.iter().cloned().collect();
+            assert_eq!(index.len(), n);
+            for _ in 0..200 {
+                let path = if next(2) == 0 { "chr1" } else { "chr2" };
+                let start = next(11_000);
+                let query = StringRegion::from_triple((path, start, start + next(500)));
+                let mut expected: Vec<usize> = entries
+                    .iter()
+                    .filter(|(region, _)| {
+                        region.path() == path
+                            && region.start().max(query.start()) < region.end().min(query.end())
+                    })
+                    .map(|(_, i)| *i)
+                    .collect();
+                let starts: Vec<u64> = index.query(&query).map(|(r, _)| r.start()).collect();
+                assert!(starts.windows(2).all(|w| w[0] <= w[1]));
+                let mut found: Vec<usize> = index.query(&query).map(|(_, i)| *i).collect();
+                assert_eq!(index.count(&query), expected.len(), "{}", query);
+                expected.sort_unstable();
+                found.sort_unstable();
+                assert_eq!(found, expected, "{}", query);
+                let pos_hits = index.query_pos(path, start).count();
+                let pos_expected = entries
+                    .iter()
+                    .filter(|(region, _)| region.contains_pos(path, start))
+                    .count();
+                assert_eq!(pos_hits, pos_expected);
+            }
+        }
     }
 
-    pub fn set_start(&mut self, start: u64) {
-        assert!(
-            start <= self.end,
-            "Region: start should not be greater than end ({} > {})",
-            start,
-            self.end
+    #[test]
+    fn region_index_query() {
+        let index = RegionIndex::from_regions(vec![
+            StringRegion::from_triple(("chr1", 300, 100)),
+            StringRegion::from_triple(("chr1", 250, 400)),
+            StringRegion::from_triple(("chr1", 500, 500)),
+            StringRegion::from_triple(("chr2", 0, 1000)),
+        ]);
+        let query = StringRegion::from_triple(("chr1", 290, 300));
+        let hits: Vec<String> = index.query(&query).map(|(r, _)| r.to_string()).collect();
+        assert_eq!(hits, vec!["chr1:300-100", "chr1:250-400"]);
+        assert_eq!(index.count(&query), 2);
+        assert_eq!(index.query_pos("chr1", 300).count(), 1);
+        assert_eq!(index.query_pos("chr1", 500).count(), 0);
+        assert_eq!(
+            index.count(&StringRegion::from_triple(("chr1", 450, 550))),
+            0
         );
-        self.start = start;
+        assert_eq!(
+            index.count(&StringRegion::from_triple(("chr1", 200, 200))),
+            0
+        );
+        assert_eq!(index.query_pos("chr3", 0).count(), 0);
+        let empty: RegionIndex<()> = RegionIndex::new(vec![]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.count(&query), 0);
     }
 
-    pub fn set_end(&mut self, end: u64) {
-        assert!(
-            self.start <= end,
-            "Region: start should not be greater than end ({} > {})",
-            self.start,
-            end
+    #[test]
+    fn string_region_parse_relaxed() {
+        let expected = StringRegion::from_triple(("chr1", 100, 200));
+        for input in [
+            "chr1:100-200",
+            "chr1:100..200",
+            "chr1_100_200",
+            "chr1:100_200",
+            "chr1-100-200",
+            " chr1:100..200\n",
+        ] {
+            assert_eq!(
+                StringRegion::parse_relaxed(input),
+                Ok(expected.clone()),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            StringRegion::parse_relaxed("chrUn_KI270302v1_100_200"),
+            Ok(StringRegion::from_triple(("chrUn_KI270302v1", 100, 200)))
         );
-        self.end = end;
+        assert_eq!(
+            StringRegion::parse_relaxed("chrUn_KI270302v1:100..200"),
+            Ok(StringRegion::from_triple(("chrUn_KI270302v1", 100, 200)))
+        );
+        assert_eq!(
+            StringRegion::parse_relaxed("chr1:200..100"),
+            Ok(StringRegion::from_triple(("chr1", 200, 100)))
+        );
+        assert!(StringRegion::parse_relaxed("chr1:100").is_err());
+        assert!(StringRegion::parse_relaxed("chr1:100...200").is_err());
+        assert!(StringRegion::parse_relaxed("chr1:1-99999999999999999999").is_err());
     }
 
-    pub fn contains(&self, ref_id: u64, pos: u64) -> bool {
-        self.ref_id == ref_id && self.start <= pos && pos < self.end
+    #[test]
+    fn coverage_over_sets() {
+        let nested = [
+            Region::new(0, 0, 100),
+            Region::new(0, 20, 80),
+            Region::new(0, 40, 60),
+        ];
+        assert_eq!(
+            coverage(&nested),
+            vec![
+                (Region::new(0, 0, 20), 1),
+                (Region::new(0, 20, 40), 2),
+                (Region::new(0, 40, 60), 3),
+                (Region::new(0, 60, 80), 2),
+                (Region::new(0, 80, 100), 1),
+            ]
+        );
+        let staircase = [
+            Region::new(0, 30, 60),
+            Region::new(0, 0, 30),
+            Region::new(0, 10, 40),
+            Region::new(0, 20, 50),
+        ];
+        assert_eq!(
+            coverage(&staircase),
+            vec![
+                (Region::new(0, 0, 10), 1),
+                (Region::new(0, 10, 20), 2),
+                (Region::new(0, 20, 40), 3),
+                (Region::new(0, 40, 50), 2),
+                (Region::new(0, 50, 60), 1),
+            ]
+        );
+        let disjoint = [
+            Region::new(1, 0, 10),
+            Region::new(0, 50, 60),
+            Region::new(0, 10, 20),
+            Region::new(0, 20, 30),
+            Region::new(0, 40, 40),
+        ];
+        assert_eq!(
+            coverage(&disjoint),
+            vec![
+                (Region::new(0, 10, 30), 1),
+                (Region::new(0, 50, 60), 1),
+                (Region::new(1, 0, 10), 1),
+            ]
+        );
+        assert!(coverage(&[]).is_empty());
+
+        let by_path = coverage_string(&[
+            StringRegion::from_triple(("chr1", 20, 0)),
+            StringRegion::from_triple(("chr1", 10, 30)),
+            StringRegion::from_triple(("chr2", 5, 6)),
+        ]);
+        assert_eq!(by_path.len(), 2);
+        assert_eq!(
+            by_path["chr1"],
+            vec![
+                (StringRegion::from_triple(("chr1", 0, 10)), 1),
+                (StringRegion::from_triple(("chr1", 10, 20)), 2),
+                (StringRegion::from_triple(("chr1", 20, 30)), 1),
+            ]
+        );
+        assert_eq!(
+            by_path["chr2"],
+            vec![(StringRegion::from_triple(("chr2", 5, 6)), 1)]
+        );
     }
 
-    pub fn include(&self, range: &Region) -> bool {
-        self.ref_id == range.ref_id && self.start <= range.start && range.end < self.end
+    #[test]
+    fn with_ref_id_and_path() {
+        let region = Region::new(3, 10, 20);
+        let remapped: Vec<Region> = [region.clone()]
+            .iter()
+            .map(|r| r.with_ref_id(r.ref_id() + 1))
+            .collect();
+        assert_eq!(remapped, vec![Region::new(4, 10, 20)]);
+        assert_eq!(region.ref_id(), 3);
+        let renamed = StringRegion::from_triple(("1", 20, 10)).with_path("chr1");
+        assert_eq!(renamed.to_string(), "chr1:20-10");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn string_region_merge_overlaps() {
+        let mut annotated = StringRegion::from_triple(("chr1", 150, 100));
+        annotated.set_name(Some("b".to_string()));
+        let input = vec![
+            StringRegion::from_triple(("chr2", 100, 120)),
+            StringRegion::from_triple(("chr1", 140, 300)),
+            annotated,
+            StringRegion::from_triple(("chr1", 0, 110)),
+            StringRegion::from_triple(("chr2", 0, 100)),
+            StringRegion::from_triple(("chr1", 305, 400)),
+        ];
+        let result = merged(&input, 0);
+        assert_eq!(
+            result,
+            vec![
+                StringRegion::from_triple(("chr1", 0, 300)),
+                StringRegion::from_triple(("chr1", 305, 400)),
+                StringRegion::from_triple(("chr2", 0, 120)),
+            ]
+        );
+        assert!(result.iter().all(|r| !r.inverted() && r.name().is_none()));
+        assert_eq!(input.len(), 6);
 
-    fn region_format(path: &str) -> String {
-        format!("{}", StringRegion::new(path).unwrap())
+        let mut regions = input;
+        merge_overlaps(&mut regions, 5);
+        assert_eq!(
+            regions,
+            vec![
+                StringRegion::from_triple(("chr1", 0, 400)),
+                StringRegion::from_triple(("chr2", 0, 120)),
+            ]
+        );
+        let mut empty: Vec<StringRegion> = vec![];
+        merge_overlaps(&mut empty, 0);
+        assert!(empty.is_empty());
     }
 
     #[test]
-    fn region_works() {
-        assert_eq!(StringRegion::new("").ok(), None);
-        assert_eq!(StringRegion::new(":10-20").ok(), None);
+    fn region_gaps() {
+        let target = Region::new(0, 0, 100);
+        let intervals = [
+            Region::new(0, 60, 70),
+            Region::new(0, 20, 40),
+            Region::new(1, 0, 100),
+        ];
+        let gaps = target.gaps(&intervals);
         assert_eq!(
-            StringRegion::new("chr1:12000-12001").ok(),
-            Some(StringRegion {
-                path: "chr1".to_string(),
-                start: 12000,
-                end: 12001
+            gaps,
+            vec![
+                Region::new(0, 0, 20),
+                Region::new(0, 40, 60),
+                Region::new(0, 70, 100),
+            ]
+        );
+        let zero_runs: Vec<Region> = target
+            .coverage(&intervals)
+            .into_iter()
+            .filter(|&(_, _, depth)| depth == 0)
+            .map(|(start, end, _)| Region::new(0, start, end))
+            .collect();
+        assert_eq!(gaps, zero_runs);
+        assert!(target.gaps(&[Region::new(0, 0, 100)]).is_empty());
+    }
+
+    #[test]
+    fn string_region_subtract_sets() {
+        let mut target = StringRegion::from_triple(("chr1", 100, 0));
+        target.set_name(Some("target".to_string()));
+        let a = vec![
+            target,
+            StringRegion::from_triple(("chr2", 10, 20)),
+            StringRegion::from_triple(("chr3", 0, 50)),
+        ];
+        let b = vec![
+            StringRegion::from_triple(("chr1", 60, 70)),
+            StringRegion::from_triple(("chr1", 20, 30)),
+            StringRegion::from_triple(("chr1", 25, 40)),
+            StringRegion::from_triple(("chr2", 0, 100)),
+            StringRegion::from_triple(("chr4", 0, 100)),
+        ];
+        let fragments = subtract_sets(&a, &b);
+        let expected = vec![
+            StringRegion::from_triple(("chr1", 20, 0)),
+            StringRegion::from_triple(("chr1", 60, 40)),
+            StringRegion::from_triple(("chr1", 100, 70)),
+            StringRegion::from_triple(("chr3", 0, 50)),
+        ];
+        assert_eq!(fragments, expected);
+        assert!(fragments[..3].iter().all(|f| f.name() == Some("target")));
+        let naive: Vec<StringRegion> = a
+            .iter()
+            .flat_map(|region| {
+                b.iter().fold(vec![region.clone()], |pieces, mask| {
+                    pieces.iter().flat_map(|p| p.subtract(mask)).collect()
+                })
             })
+            .collect();
+        assert_eq!(fragments, naive);
+
+        assert_eq!(
+            subtract_sets_min_len(&a, &b, 21),
+            vec![
+                StringRegion::from_triple(("chr1", 100, 70)),
+                StringRegion::from_triple(("chr3", 0, 50)),
+            ]
         );
+        assert!(subtract_sets(&a[1..2], &b).is_empty());
+        assert_eq!(subtract_sets(&a, &[]), a);
+    }
+
+    #[test]
+    fn string_region_extend_capped() {
+        let mut near_start = StringRegion::from_triple(("chrM", 10, 20));
+        near_start.extend_capped(50, 100);
+        assert_eq!(near_start, StringRegion::from_triple(("chrM", 0, 70)));
+        let mut near_end = StringRegion::from_triple(("chrM", 90, 80));
+        near_end.extend_capped(50, 100);
+        assert_eq!(near_end, StringRegion::from_triple(("chrM", 100, 30)));
+        let mut both = StringRegion::from_triple(("chrM", 40, 60));
+        both.extend_capped(1000, 100);
+        assert_eq!(both, StringRegion::from_triple(("chrM", 0, 100)));
+        let mut past_end = StringRegion::from_triple(("chrM", 150, 160));
+        past_end.extend_capped(10, 100);
+        assert_eq!((past_end.start(), past_end.end()), (100, 100));
+    }
+
+    #[test]
+    fn intersect_sorted_matches_brute_force() {
+        let mut seed: u64 = 7;
+        let mut next = move |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % bound
+        };
+        let mut random_regions = |n: usize| {
+            let mut regions: Vec<Region> = (0..n)
+                .map(|_| {
+                    let start = next(1000);
+                    Region::new(next(2), start, start + next(200))
+                })
+                .collect();
+            regions.sort_by_key(|r| (r.ref_id(), r.start()));
+            regions
+        };
+        for (n, m) in [(0, 5), (5, 0), (1, 1), (50, 80), (300, 200)] {
+            let (a, b) = (random_regions(n), random_regions(m));
+            let mut found = intersect_sorted(&a, &b).unwrap();
+            found.sort_by_key(|&(i, j, _)| (i, j));
+            let mut expected = vec![];
+            for (i, x) in a.iter().enumerate() {
+                for (j, y) in b.iter().enumerate() {
+                    if let Some(overlap) = x.intersect(y) {
+                        expected.push((i, j, overlap));
+                    }
+                }
+            }
+            assert_eq!(found, expected, "n = {}, m = {}", n, m);
+        }
+
+        let unsorted = [Region::new(0, 50, 60), Region::new(0, 10, 20)];
         assert_eq!(
-            StringRegion::new("chr1:1200943-1201000").ok(),
-            Some(StringRegion {
-                path: "chr1".to_string(),
-                start: 1200943,
-                end: 1201000
+            intersect_sorted(&unsorted, &[]),
+            Err(RegionError::Unsorted { input: 0, index: 1 })
+        );
+        assert_eq!(
+            intersect_sorted(&[], &unsorted),
+            Err(RegionError::Unsorted { input: 1, index: 1 })
+        );
+    }
+
+    #[test]
+    fn intersect_sorted_string_regions() {
+        let a = [
+            StringRegion::from_triple(("chr1", 0, 100)),
+            StringRegion::from_triple(("chr1", 10, 20)),
+            StringRegion::from_triple(("chr2", 0, 10)),
+        ];
+        let b = [
+            StringRegion::from_triple(("chr1", 15, 5)),
+            StringRegion::from_triple(("chr1", 90, 200)),
+            StringRegion::from_triple(("chr3", 0, 10)),
+        ];
+        let mut pairs = intersect_sorted_string(&a, &b).unwrap();
+        pairs.sort_by_key(|&(i, j, _)| (i, j));
+        assert_eq!(
+            pairs,
+            vec![
+                (0, 0, StringRegion::from_triple(("chr1", 5, 15))),
+                (0, 1, StringRegion::from_triple(("chr1", 90, 100))),
+                (1, 0, StringRegion::from_triple(("chr1", 10, 15))),
+            ]
+        );
+        assert_eq!(
+            intersect_sorted_string(&[a[2].clone(), a[0].clone()], &b),
+            Err(RegionError::Unsorted { input: 0, index: 1 })
+        );
+    }
+
+    #[test]
+    fn region_overlaps_point_and_covers_range() {
+        let region = Region::new(1, 100, 200);
+        assert!(region.overlaps_point(1, 100) && region.overlaps_point(1, 199));
+        assert!(!region.overlaps_point(1, 200) && !region.overlaps_point(0, 150));
+        assert!(region.covers_range(1, 100, 200));
+        assert!(region.covers_range(1, 150, 200));
+        assert!(!region.covers_range(1, 150, 201));
+        assert!(!region.covers_range(1, 99, 150));
+        assert!(!region.covers_range(2, 150, 160));
+        assert!(!region.covers_range(1, 160, 150));
+    }
+
+    #[test]
+    fn closest_with_ties_on_both_sides() {
+        let targets = [
+            Region::new(0, 0, 10),
+            Region::new(0, 20, 30),
+            Region::new(0, 50, 60),
+            Region::new(0, 70, 80),
+            Region::new(1, 35, 45),
+        ];
+        assert_eq!(closest(&Region::new(0, 40, 40), &targets), vec![1, 2]);
+        assert_eq!(closest(&Region::new(0, 25, 55), &targets), vec![1, 2]);
+        assert_eq!(closest(&Region::new(0, 33, 40), &targets), vec![1]);
+        assert_eq!(closest(&Region::new(0, 200, 210), &targets), vec![3]);
+        assert_eq!(
+            closest(&Region::new(2, 0, 10), &targets),
+            Vec::<usize>::new()
+        );
+        assert_eq!(closest(&Region::new(0, 0, 10), &[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn k_nearest_reports_gaps_in_order() {
+        let targets = [
+            Region::new(0, 0, 10),
+            Region::new(0, 20, 30),
+            Region::new(0, 50, 60),
+            Region::new(0, 70, 80),
+        ];
+        let query = Region::new(0, 40, 45);
+        assert_eq!(k_nearest(&query, &targets, 2), vec![(2, 5), (1, 10)]);
+        assert_eq!(
+            k_nearest(&query, &targets, 10),
+            vec![(2, 5), (1, 10), (3, 25), (0, 30)]
+        );
+        assert_eq!(k_nearest(&query, &targets, 0), vec![]);
+    }
+
+    #[test]
+    fn closest_string_is_keyed_by_path() {
+        let targets = [
+            StringRegion::from_triple(("chr1", 100, 200)),
+            StringRegion::from_triple(("chr2", 0, 10)),
+            StringRegion::from_triple(("chr2", 300, 400)),
+        ];
+        let query = StringRegion::from_triple(("chr2", 150, 160));
+        assert_eq!(closest_string(&query, &targets), vec![1, 2]);
+        let query = StringRegion::from_triple(("chr2", 150, 200));
+        assert_eq!(closest_string(&query, &targets), vec![2]);
+        assert_eq!(
+            k_nearest_string(&query, &targets, 2),
+            vec![(2, 100), (1, 140)]
+        );
+    }
+
+    #[test]
+    fn parse_or_whole_accepts_bare_contigs() {
+        let to_id = |name: &str| if name == "chr1" { Some(0) } else { None };
+        let contig_len = |name: &str| if name == "chr1" { Some(1000) } else { None };
+        assert_eq!(
+            Region::parse_or_whole("chr1:10-20", to_id, contig_len).unwrap(),
+            Region::new(0, 10, 20)
+        );
+        assert_eq!(
+            Region::parse_or_whole("chr1", to_id, contig_len).unwrap(),
+            Region::new(0, 0, 1000)
+        );
+        assert!(Region::parse_or_whole("chr1", to_id, |_| None).is_err());
+        assert!(Region::parse_or_whole("chr2", to_id, contig_len).is_err());
+        assert!(Region::parse_or_whole("chr1:", to_id, contig_len).is_err());
+    }
+
+    #[test]
+    fn stranded_region_flanks_follow_strand() {
+        let forward = Region::new(0, 100, 200).into_stranded(Strand::Forward);
+        assert_eq!(forward.upstream(30).region(), &Region::new(0, 70, 100));
+        assert_eq!(forward.downstream(30).region(), &Region::new(0, 200, 230));
+        assert_eq!(forward.upstream(30).strand(), Strand::Forward);
+
+        let reverse = Region::new(0, 100, 200).into_stranded(Strand::Reverse);
+        assert_eq!(reverse.upstream(30).region(), &Region::new(0, 200, 230));
+        assert_eq!(reverse.downstream(30).region(), &Region::new(0, 70, 100));
+        assert_eq!(reverse.downstream(30).strand(), Strand::Reverse);
+        assert_eq!(reverse.downstream(500).region(), &Region::new(0, 0, 100));
+
+        let unknown = Region::new(0, 100, 200).into_stranded(Strand::Unknown);
+        assert_eq!(unknown.upstream(30).region(), &Region::new(0, 70, 100));
+        assert_eq!(unknown.into_region(), Region::new(0, 100, 200));
+    }
+
+    #[test]
+    fn cmp_natural_orders_numbers_by_value() {
+        assert_eq!(cmp_natural("chr2", "chr10"), Ordering::Less);
+        assert_eq!(cmp_natural("chr10", "chr10"), Ordering::Equal);
+        assert_eq!(cmp_natural("chr1", "chr1_alt"), Ordering::Less);
+        assert_eq!(cmp_natural("chr9", "chrX"), Ordering::Less);
+        assert_eq!(cmp_natural("chr01", "chr1"), Ordering::Less);
+    }
+
+    #[test]
+    fn merge_sorted_interleaves_sources() {
+        let source = |regions: &[(&str, u64, u64)]| {
+            regions
+                .iter()
+                .map(|&t| StringRegion::from_triple(t))
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        let a = source(&[("chr1", 0, 10), ("chr2", 50, 60), ("chr10", 0, 5)]);
+        let b = source(&[("chr1", 0, 20), ("chr1", 30, 40)]);
+        let c = source(&[("chr2", 10, 20), ("chr10", 0, 7)]);
+        let merged: Vec<_> = merge_sorted(vec![a, b, c])
+            .map(|r| (r.path.clone(), r.start, r.end))
+            .collect();
+        let expected = [
+            ("chr1", 0, 10),
+            ("chr1", 0, 20),
+            ("chr1", 30, 40),
+            ("chr2", 10, 20),
+            ("chr2", 50, 60),
+            ("chr10", 0, 5),
+            ("chr10", 0, 7),
+        ];
+        assert_eq!(merged.len(), expected.len());
+        for (got, want) in merged.iter().zip(expected.iter()) {
+            assert_eq!((got.0.as_str(), got.1, got.2), *want);
+        }
+        assert_eq!(merge_sorted(Vec::<std::vec::IntoIter<_>>::new()).count(), 0);
+    }
+
+    #[test]
+    fn merge_sorted_checked_detects_disorder() {
+        let a = vec![
+            StringRegion::from_triple(("chr1", 0, 10)),
+            StringRegion::from_triple(("chr10", 0, 10)),
+            StringRegion::from_triple(("chr2", 0, 10)),
+        ];
+        let b = vec![StringRegion::from_triple(("chr1", 5, 10))];
+        let results: Vec<_> = merge_sorted_checked(vec![b.into_iter(), a.into_iter()]).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(|r| r.is_ok()));
+        assert_eq!(
+            results[3],
+            Err(RegionError::Unsorted { input: 1, index: 2 })
+        );
+
+        let sorted = vec![StringRegion::from_triple(("chr1", 0, 10))];
+        let results: Vec<_> = merge_sorted_checked(vec![sorted.into_iter()]).collect();
+        assert_eq!(
+            results,
+            vec![Ok(StringRegion::from_triple(("chr1", 0, 10)))]
+        );
+    }
+
+    #[test]
+    fn parse_coordinate_accepts_commas_suffixes_and_exponents() {
+        assert_eq!(parse_coordinate("1200"), Ok(1200));
+        assert_eq!(parse_coordinate("1,200"), Ok(1200));
+        assert_eq!(parse_coordinate("12,345,678"), Ok(12_345_678));
+        assert_eq!(parse_coordinate("1.2M"), Ok(1_200_000));
+        assert_eq!(parse_coordinate("1.3e6"), Ok(1_300_000));
+        assert_eq!(parse_coordinate("2E+3"), Ok(2000));
+        assert_eq!(parse_coordinate("1.5e2k"), Ok(150_000));
+        assert_eq!(parse_coordinate("1.50k"), Ok(1500));
+        assert_eq!(parse_coordinate("3g"), Ok(3_000_000_000));
+        assert_eq!(parse_coordinate("0.0"), Ok(0));
+        for bad in &[
+            "",
+            "k",
+            "-1",
+            "1.5",
+            "1.2345k",
+            "1.",
+            "1e",
+            "1e-3",
+            "1.2,3",
+            "1,2M",
+            "1,2345",
+            "1,23,456",
+            "1,",
+            "1x",
+            "20000000000G",
+            "1e9223372036854775807k",
+            "10e9223372036854775807",
+        ] {
+            assert!(
+                matches!(
+                    parse_coordinate(bad),
+                    Err(RegionParseError::InvalidPosition(_))
+                ),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn region_constructors_share_coordinate_grammar() {
+        let region = StringRegion::new("chr1:12,000k-1.3e6").unwrap();
+        assert_eq!((region.start(), region.end()), (1_300_000, 12_000_000));
+        assert!(StringRegion::new("chr1:1,2M-1.3e6").is_err());
+        assert!(StringRegion::new("chr1:1e9223372036854775807k-2").is_err());
+        let region = StringRegion::new("chr1:1.2M-1.3e6:+").unwrap();
+        assert_eq!((region.start(), region.end()), (1_200_000, 1_300_000));
+        assert_eq!(region.strand(), Some(Strand::Forward));
+        let region = StringRegion::new("chr1 1,000 2k").unwrap();
+        assert_eq!((region.start(), region.end()), (1000, 2000));
+        let region = StringRegion::parse_relaxed("chr1_1k..2k").unwrap();
+        assert_eq!((region.start(), region.end()), (1000, 2000));
+        let optional = OptionalRegion::new("chr1:1.5k-").unwrap();
+        assert_eq!((optional.start, optional.end), (Some(1500), None));
+        assert_eq!(
+            Region::parse("chr1:1k-2,000", |_| Some(0)).unwrap(),
+            Region::new(0, 1000, 2000)
+        );
+        let borrowed = BorrowedRegion::parse("chr1:1,000-1.5k").unwrap();
+        assert_eq!((borrowed.start, borrowed.end), (1000, 1500));
+        assert!(StringRegion::new("chr1:1.5-10").is_err());
+    }
+
+    #[test]
+    fn partition_by_path_keeps_first_seen_order() {
+        let regions: Vec<_> = [
+            ("chr2", 50, 60),
+            ("chr1", 30, 40),
+            ("chrX", 0, 10),
+            ("chr2", 10, 20),
+            ("chr1", 0, 5),
+            ("chr2", 30, 35),
+        ]
+        .iter()
+        .map(|&t| StringRegion::from_triple(t))
+        .collect();
+        let starts = |groups: &[(String, Vec<StringRegion>)]| -> Vec<(String, Vec<u64>)> {
+            groups
+                .iter()
+                .map(|(path, group)| (path.clone(), group.iter().map(|r| r.start()).collect()))
+                .collect()
+        };
+        assert_eq!(
+            starts(&partition_by_path(regions.clone(), false)),
+            vec![
+                ("chr2".to_string(), vec![50, 10, 30]),
+                ("chr1".to_string(), vec![30, 0]),
+                ("chrX".to_string(), vec![0]),
+            ]
+        );
+        assert_eq!(
+            starts(&partition_by_path(regions, true)),
+            vec![
+                ("chr2".to_string(), vec![10, 30, 50]),
+                ("chr1".to_string(), vec![0, 30]),
+                ("chrX".to_string(), vec![0]),
+            ]
+        );
+        assert!(partition_by_path(vec![], true).is_empty());
+    }
+
+    #[test]
+    fn group_by_path_borrows_runs() {
+        let regions: Vec<_> = [
+            ("chr1", 0, 10),
+            ("chr1", 20, 30),
+            ("chr2", 0, 10),
+            ("chrX", 5, 10),
+            ("chrX", 1, 2),
+        ]
+        .iter()
+        .map(|&t| StringRegion::from_triple(t))
+        .collect();
+        let groups = group_by_path(&regions).unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].0, "chr1");
+        assert_eq!(groups[0].1, &regions[0..2]);
+        assert_eq!(groups[1].1, &regions[2..3]);
+        assert_eq!(groups[2].1, &regions[3..5]);
+        assert_eq!(group_by_path(&[]).unwrap().len(), 0);
+
+        let mut interleaved = regions;
+        interleaved.push(StringRegion::from_triple(("chr1", 50, 60)));
+        assert_eq!(
+            group_by_path(&interleaved),
+            Err(RegionError::Unsorted { input: 0, index: 5 })
+        );
+    }
+
+    #[test]
+    fn contains_checked_reports_wrong_reference() {
+        let region = Region::new(1, 100, 200);
+        assert_eq!(region.contains_checked(1, 100), Ok(true));
+        assert_eq!(region.contains_checked(1, 200), Ok(false));
+        assert_eq!(region.contains_checked(1, 50), Ok(false));
+        assert_eq!(
+            region.contains_checked(2, 150),
+            Err(RegionError::DifferentReference)
+        );
+        assert!(!region.contains(2, 150));
+    }
+
+    #[test]
+    fn ensure_sorted_reports_first_break() {
+        let mut regions = vec![
+            Region::new(0, 10, 20),
+            Region::new(0, 10, 30),
+            Region::new(0, 40, 50),
+            Region::new(0, 35, 60),
+            Region::new(1, 0, 5),
+        ];
+        assert!(!is_sorted(&regions));
+        assert_eq!(
+            ensure_sorted(&regions),
+            Err(RegionError::Unsorted { input: 0, index: 3 })
+        );
+        sort_regions(&mut regions);
+        assert!(is_sorted(&regions));
+        assert_eq!(ensure_sorted(&regions), Ok(()));
+        assert!(is_sorted(&[]));
+    }
+
+    #[test]
+    fn sort_string_regions_by_name_order() {
+        let mut a = StringRegion::from_triple(("chr2", 0, 10));
+        a.set_name(Some("first".to_string()));
+        let mut b = StringRegion::from_triple(("chr2", 0, 10));
+        b.set_name(Some("second".to_string()));
+        let regions = vec![
+            StringRegion::from_triple(("chr10", 5, 6)),
+            a,
+            StringRegion::from_triple(("chrM", 0, 1)),
+            StringRegion::from_triple(("chr1", 7, 8)),
+            b,
+        ];
+        let paths = |regions: &[StringRegion]| -> Vec<String> {
+            regions.iter().map(|r| r.path.clone()).collect()
+        };
+
+        let mut lexical = regions.clone();
+        sort_string_regions(&mut lexical, NameOrder::Lexical);
+        assert_eq!(paths(&lexical), ["chr1", "chr10", "chr2", "chr2", "chrM"]);
+        assert!(is_sorted_string(&lexical, NameOrder::Lexical));
+        assert_eq!(
+            ensure_sorted_string(&lexical, NameOrder::Natural),
+            Err(RegionError::Unsorted { input: 0, index: 2 })
+        );
+
+        let mut natural = regions.clone();
+        sort_string_regions(&mut natural, NameOrder::Natural);
+        assert_eq!(paths(&natural), ["chr1", "chr2", "chr2", "chr10", "chrM"]);
+        assert_eq!(natural[1].name(), Some("first"));
+        assert_eq!(natural[2].name(), Some("second"));
+
+        let karyotype = Karyotype::new(vec!["chrM", "chr1", "chr2"]);
+        let mut assembly = regions;
+        sort_string_regions(&mut assembly, NameOrder::Karyotype(&karyotype));
+        assert_eq!(paths(&assembly), ["chrM", "chr1", "chr2", "chr2", "chr10"]);
+        assert!(is_sorted_string(
+            &assembly,
+            NameOrder::Karyotype(&karyotype)
+        ));
+        assert_eq!(karyotype.rank("chr2"), Some(2));
+        assert_eq!(karyotype.rank("chr10"), None);
+    }
+
+    #[test]
+    fn region_map_returns_overlapping_values() {
+        let mut genes = RegionMap::new();
+        genes.insert(Region::new(0, 1000, 5000), "BRCA2");
+        genes.insert(Region::new(0, 100, 300), "TP53");
+        genes.insert(Region::new(0, 4000, 4500), "ZAR1L");
+        genes.insert(Region::new(1, 100, 300), "EGFR");
+        assert_eq!(genes.len(), 4);
+
+        let hits = genes.get_overlapping(&Region::new(0, 250, 4200));
+        let names: Vec<&str> = hits.iter().map(|&(_, name)| *name).collect();
+        assert_eq!(names, ["TP53", "BRCA2", "ZAR1L"]);
+        assert_eq!(hits[0].0, &Region::new(0, 100, 300));
+
+        let names: Vec<&str> = genes
+            .get_overlapping(&Region::new(0, 4600, 4700))
+            .into_iter()
+            .map(|(_, name)| *name)
+            .collect();
+        assert_eq!(names, ["BRCA2"]);
+        assert!(genes
+            .get_overlapping(&Region::new(0, 5000, 6000))
+            .is_empty());
+        assert!(genes.get_overlapping(&Region::new(2, 0, 10000)).is_empty());
+
+        let map: RegionMap<u32> = vec![(Region::new(0, 0, 10), 1)].into_iter().collect();
+        assert_eq!(map.iter().next(), Some((&Region::new(0, 0, 10), &1)));
+        assert!(RegionMap::<u32>::new().is_empty());
+    }
+
+    #[test]
+    fn dedup_regions_exact_and_by_coordinates() {
+        let regions: Vec<StringRegion> = [
+            "chr1:100-200",
+            "chr2:0-10",
+            "chr1:200-100",
+            "chr1:100-200",
+            "1:100-200",
+        ]
+        .iter()
+        .map(|s| StringRegion::new(s).unwrap())
+        .collect();
+        let mut exact = regions.clone();
+        assert_eq!(dedup_regions(&mut exact), 1);
+        let shown: Vec<String> = exact.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            shown,
+            ["1:100-200", "chr1:100-200", "chr1:200-100", "chr2:0-10"]
+        );
+
+        let mut loose = regions;
+        assert_eq!(dedup_by_coordinates(&mut loose), 3);
+        let shown: Vec<String> = loose.iter().map(|r| r.to_string()).collect();
+        assert_eq!(shown, ["chr1:100-200", "chr2:0-10"]);
+    }
+
+    #[test]
+    fn normalize_set_chains_all_steps() {
+        let messy: Vec<StringRegion> = [
+            "chr1:100-200",
+            "1:200-100",
+            "MT:0-50",
+            "chrM:0-50",
+            "chr1:150-300",
+            "chr1:100-200",
+            "chr2:0-10",
+        ]
+        .iter()
+        .map(|s| StringRegion::new(s).unwrap())
+        .collect();
+        let mut opts = NormalizeOptions {
+            prefix: ChrPrefix::Strip,
+            by_coordinates: true,
+            merge_gap: Some(0),
+            ..NormalizeOptions::default()
+        };
+        opts.aliases.insert("MT".to_string(), "chrM".to_string());
+        let (normalized, stats) = normalize_set(messy.clone(), &opts);
+        let shown: Vec<String> = normalized.iter().map(|r| r.to_string()).collect();
+        assert_eq!(shown, ["1:100-300", "2:0-10", "M:0-50"]);
+        assert_eq!(
+            stats,
+            NormalizeStats {
+                duplicates: 3,
+                merged: 1
+            }
+        );
+
+        let (normalized, stats) = normalize_set(messy, &NormalizeOptions::default());
+        assert_eq!(normalized.len(), 6);
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stats.merged, 0);
+
+        let added = normalize_set(
+            vec![StringRegion::new("X:1-2").unwrap()],
+            &NormalizeOptions {
+                prefix: ChrPrefix::Add,
+                ..NormalizeOptions::default()
+            },
+        );
+        assert_eq!(added.0[0].to_string(), "chrX:1-2");
+    }
+
+    #[test]
+    fn string_region_extract_embedded() {
+        let region = StringRegion::extract_embedded("gene|chr1:1000-2000").unwrap();
+        assert_eq!(region, StringRegion::new("chr1:1000-2000").unwrap());
+        let region = StringRegion::extract_embedded("ID=tx1;locus=chr2:1,500-3k:-;note").unwrap();
+        assert_eq!(region, StringRegion::new("chr2:1500-3000:-").unwrap());
+        let region = StringRegion::extract_embedded("(chrX:5-10) and chr3:1-2").unwrap();
+        assert_eq!(region.to_string(), "chrX:5-10");
+        let region = StringRegion::extract_embedded("see chr1:100-200, then").unwrap();
+        assert_eq!(region, StringRegion::from_triple(("chr1", 100, 200)));
+        let region = StringRegion::extract_embedded("hits chr1:1,000-2,000, chr2:5-6").unwrap();
+        assert_eq!(region, StringRegion::from_triple(("chr1", 1000, 2000)));
+        assert_eq!(StringRegion::extract_embedded("BRCA2 exon 11"), None);
+        assert_eq!(StringRegion::extract_embedded("ratio 1:2"), None);
+        assert_eq!(StringRegion::extract_embedded(""), None);
+    }
+
+    #[test]
+    fn region_stats_summarizes_lengths() {
+        let regions: Vec<_> = [
+            ("chr1", 0, 100),
+            ("chr1", 50, 150),
+            ("chr1", 140, 160),
+            ("chr2", 0, 40),
+        ]
+        .iter()
+        .map(|&t| StringRegion::from_triple(t))
+        .collect();
+        let stats = region_stats(regions.iter());
+        assert_eq!(
+            stats,
+            RegionStats {
+                count: 4,
+                total_len: 260,
+                min_len: Some(20),
+                max_len: Some(100),
+                mean_len: Some(65.0),
+                median_len: Some(70.0),
+                n50: Some(100),
+            }
+        );
+        assert_eq!(total_len_merged(regions.iter()), 200);
+        assert_eq!(region_stats(regions[..3].iter()).median_len, Some(100.0));
+    }
+
+    #[test]
+    fn region_stats_of_empty_set() {
+        let stats = region_stats(std::iter::empty());
+        assert_eq!(stats, RegionStats::default());
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.mean_len, None);
+        assert_eq!(stats.n50, None);
+        assert_eq!(total_len_merged(std::iter::empty()), 0);
+    }
+
+    #[test]
+    fn region_display_bounds() {
+        let region = Region::new(0, 0, 100);
+        assert_eq!(region.display_bounds(false), (0, 100));
+        assert_eq!(region.display_bounds(true), (1, 100));
+        let base = Region::new(0, 9, 10);
+        assert_eq!(base.display_bounds(true), (10, 10));
+        let empty = Region::new(0, 10, 10);
+        assert_eq!(empty.display_bounds(true), (11, 10));
+    }
+
+    #[test]
+    fn coverage_histogram_on_toy_genome() {
+        let mut sizes = ChromSizes::new();
+        sizes.insert("chr1", 100);
+        sizes.insert("chr2", 100);
+        let regions: Vec<_> = [
+            ("chr1", 0, 50),
+            ("chr1", 25, 75),
+            ("chr2", 90, 120),
+            ("chrUn", 0, 10),
+        ]
+        .iter()
+        .map(|&t| StringRegion::from_triple(t))
+        .collect();
+
+        let (histogram, clamped) = coverage_histogram(&regions, Some(&sizes));
+        let expected: BTreeMap<u32, u64> = [(0, 115), (1, 60), (2, 25)].iter().copied().collect();
+        assert_eq!(histogram, expected);
+        assert_eq!(clamped, 2);
+
+        let (histogram, clamped) = coverage_histogram(&regions, None);
+        let expected: BTreeMap<u32, u64> = [(1, 90), (2, 25)].iter().copied().collect();
+        assert_eq!(histogram, expected);
+        assert_eq!(clamped, 0);
+
+        assert_eq!(fraction_covered(&regions, &sizes, 1), (85.0 / 200.0, 2));
+        assert_eq!(fraction_covered(&regions, &sizes, 2), (25.0 / 200.0, 2));
+        assert_eq!(fraction_covered(&regions, &sizes, 0), (1.0, 2));
+        assert_eq!(fraction_covered(&regions, &ChromSizes::new(), 1), (0.0, 4));
+    }
+
+    #[test]
+    fn region_set_extend_matches_insert() {
+        let regions: Vec<StringRegion> = (0..200u64)
+            .map(|i| {
+                let start = (i * 7919) % 1000;
+                let path = if i % 3 == 0 { "chr2" } else { "chr1" };
+                StringRegion::from_triple((path, start, start + i % 50))
             })
+            .collect();
+        let mut inserted = RegionSet::new();
+        for region in regions.iter().cloned() {
+            inserted.insert(region);
+        }
+        let collected: RegionSet = regions.iter().cloned().collect();
+        assert_eq!(collected, inserted);
+        let mut extended = RegionSet::new();
+        extended.insert(StringRegion::from_triple(("chr1", 500, 900)));
+        extended.extend(regions);
+        assert_eq!(extended.len(), 201);
+        let starts: Vec<u64> = extended.iter().map(|r| r.start()).collect();
+        let (chr1, chr2) = starts.split_at(extended.iter().filter(|r| r.path == "chr1").count());
+        assert!(chr1.windows(2).all(|w| w[0] <= w[1]));
+        assert!(chr2.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn nearest_index_finds_a_long_target_far_to_the_left() {
+        let mut targets = vec![Region::new(0, 0, 1_000), Region::new(1, 0, 5)];
+        targets.splice(1..1, (0..100).map(|i| Region::new(0, 10 + i, 11 + i)));
+        let index = NearestIndex::<Region>::new(&targets);
+        let query = Region::new(0, 500, 510);
+        assert_eq!(index.closest(&query), vec![0]);
+        assert_eq!(index.k_nearest(&query, 2), vec![(0, 0), (100, 390)]);
+        assert_eq!(index.k_nearest(&Region::new(1, 8, 9), 1), vec![(101, 3)]);
+        assert!(index.closest(&Region::new(2, 0, 1)).is_empty());
+    }
+
+    #[test]
+    fn region_map_finds_long_regions_after_incremental_inserts() {
+        let mut map: RegionMap<usize> = (0..100)
+            .map(|i| (Region::new(0, 10 * i, 10 * i + 5), i as usize))
+            .collect();
+        map.insert(Region::new(0, 0, 2_000), 100);
+        map.extend(vec![
+            (Region::new(0, 0, 2_000), 101),
+            (Region::new(3, 0, 1), 102),
+        ]);
+        assert_eq!(map.len(), 103);
+        let hits: Vec<usize> = map
+            .get_overlapping(&Region::new(0, 1_500, 1_501))
+            .into_iter()
+            .map(|(_, &v)| v)
+            .collect();
+        assert_eq!(hits, [100, 101]);
+        let hits: Vec<usize> = map
+            .get_overlapping(&Region::new(0, 992, 994))
+            .into_iter()
+            .map(|(_, &v)| v)
+            .collect();
+        assert_eq!(hits, [100, 101, 99]);
+        assert_eq!(map.iter().last(), Some((&Region::new(3, 0, 1), &102)));
+    }
+
+    #[test]
+    fn machine_formats_take_plain_integer_coordinates() {
+        assert_eq!(parse_integer_coordinate("1000"), Ok(1000));
+        let comma = RegionParseError::InvalidPosition("\"1,000\": not a plain integer".to_string());
+        assert_eq!(parse_integer_coordinate("1,000"), Err(comma.clone()));
+        assert_eq!(ChromSizes::parse("chr1 1,000"), Err(comma.clone()));
+        assert_eq!(
+            parse_bedpe_line("chr1\t1,000\t2000\tchr1\t0\t1", |_| Some(0)),
+            Err(comma)
+        );
+        let err = StringRegion::from_bed_fields(&["chr1", "1k", "2000"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid position: \"1k\": not a plain integer"
         );
+        assert!(
+            StringRegion::from_gff_fields(&["chr1", ".", "gene", "1e3", "2000", ".", "+"]).is_err()
+        );
+        assert!(matches!(
+            parse_integer_coordinate("18446744073709551616"),
+            Err(RegionParseError::InvalidPosition(_))
+        ));
     }
 
     #[test]
-    fn region_format_works() {
-        let a = "chr1:12000-12001";
-        assert_eq!(region_format(a), a);
-        let b = "10:120-120001";
-        assert_eq!(region_format(b), b);
+    fn borrowed_and_owned_parsers_agree_on_unit_words() {
+        for input in &[
+            "chr1 1 2 Mb",
+            "chr1:1-2 kb",
+            "chr1:1.5-2 Mb",
+            "chr1:100-200:- kb",
+            "chr1 1 2 bp",
+            "chr1 1 2",
+            "chr1\t1\t2\tkb",
+            "chr1 1 2 kb Mb",
+            "chr1:1-2 kb Mb",
+            "chr1:1.5-2",
+            "chr1 99999999999999999 2 Mb",
+        ] {
+            let owned = StringRegion::new(input)
+                .ok()
+                .map(|r| (r.path.clone(), r.start(), r.end(), r.strand()));
+            let borrowed = BorrowedRegion::parse(input)
+                .ok()
+                .map(|r| (r.path.to_string(), r.start, r.end, r.strand()));
+            assert_eq!(owned, borrowed, "{:?}", input);
+        }
+        let region = BorrowedRegion::parse("chr1:1.5-2 Mb").unwrap();
+        assert_eq!((region.start, region.end), (1_500_000, 2_000_000));
     }
 }